@@ -1,15 +1,34 @@
-use crate::files::{launch_executable, download_file, unzip_file, DownloadStatus};
-use crate::config::{VERSION, MAIN_TITLE, AUTHOR, CONTROLS, BOTTOM_TEXT, MAIN_MENU_OPTIONS, FILES_TO_REMOVE, Config};
+use crate::files::{create_folder, run_installer, download_file, verify_hashes, has_supported_hash, list_files, is_url, format_size, DownloadStatus};
+use crate::scheduler::{start_batch, DownloadJob};
+use crate::cache::{Cache, CACHE_FOLDER};
+use crate::extract::{ExtractPolicy, Rejected, extract_archive, check_archive, check_folder, copy_folder_checked};
+use crate::plan::{PlanLine, plan_install, removed_files, removed_folders, wipes_folders};
+use crate::signature::{is_signature_error, signature_url, verify_file};
+use crate::update::{Release, check_update, download_path, replace_executable};
+use crate::rate_limit::{rate_limit, increase_rate_limit, decrease_rate_limit};
+use crate::modloader::{ModloaderKind, fetch_loader_profile, install_version_profile, install_strategy, expected_version_id, is_version_installed, InstallStrategy};
+use crate::launcher::{write_profile, LauncherProfile};
+use crate::java::{find_java_installs, select_java, JavaInstall, JavaStatus};
+use crate::config::{VERSION, MAIN_TITLE, AUTHOR, CONTROLS, BOTTOM_TEXT, FILES_TO_REMOVE, IMPORTS_FOLDER, Config, Pack, PackFormat, get_env_path};
+use crate::manifest::{Manifest, ManifestFile, FileAction};
+use crate::record::InstallRecord;
+use crate::options::{enable_resource_packs, merge_keys};
+use crate::mrpack::{read_mrpack, MRPACK_FILE};
+use crate::curseforge::{read_curseforge, CURSEFORGE_FILE};
+use crate::packwiz::read_packwiz;
 
+use std::collections::HashMap;
+use std::env;
 use std::thread;
 use std::thread::sleep;
 use std::time::Duration;
-use std::fs::{File, remove_dir_all};
-use std::sync::Arc;
+use std::fs::{self, File, remove_dir_all, remove_file};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
 use std::io::{Write, self};
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
     style::{Attribute, Print, Color, Stylize, PrintStyledContent, StyledContent},
     execute, terminal, queue, cursor,
 };
@@ -18,7 +37,35 @@ use std::sync::mpsc;
 
 pub enum AppStatus {
     Loop,
+    Back,
     Exit,
+    /// The installer was updated and must be started again.
+    Restart,
+}
+
+/// Actions of the main menu, the options shown depend on the config.
+#[derive(Clone, Copy)]
+enum MenuAction {
+    InstallPack,
+    InstallFromFile,
+    Preview,
+    InstallModloader,
+    RemoveFiles,
+    ClearCache,
+    SelfUpdate,
+    ChangePack,
+    Quit,
+}
+
+/// Extensions of the archives shown in the file browser.
+const ARCHIVE_EXTENSIONS: &[&str] = &["zip", "mrpack"];
+
+/// A line of the file browser.
+enum BrowserEntry {
+    /// Picks the folder being browsed, for an already extracted modpack.
+    CurrentFolder,
+    Folder(PathBuf),
+    Archive(PathBuf),
 }
 
 trait UnwrapOrLog<T, E> {
@@ -41,15 +88,50 @@ pub struct Display {
     terminal_width: u16,
     terminal_height: u16,
     config: Config,
+    java_installs: Vec<JavaInstall>,
+    pack: Option<usize>,
+    cache: Arc<Mutex<Cache>>,
+    /// Archive or folder given with `--from`, installed instead of downloading the modpack.
+    from: Option<PathBuf>,
+    /// Newer release of the installer found at `update_url` when starting.
+    update: Option<Release>,
+    /// Set with `--yes`, destructive actions are done without asking.
+    assume_yes: bool,
 }
 
 impl Display {
-    pub fn open(config: Config) -> crossterm::Result<Display>{
+    pub fn open(config: Config, from: Option<PathBuf>, assume_yes: bool) -> crossterm::Result<Display>{
         execute!(io::stdout(), terminal::EnterAlternateScreen, cursor::Hide)?;
+        let mut config = config;
+        let java_installs = find_java_installs(&config.minecraft_folder);
+        config.log(format!("java installs: {:?}", &java_installs).as_str());
+        let instance_folders: Vec<String> = config.packs.iter()
+            .map(|pack| format!("{} instance folder: {}", &pack.id, &pack.instance_folder))
+            .collect();
+        instance_folders.iter().for_each(|line| config.log(line));
+        let cache = Arc::new(Mutex::new(Cache::open(&config.magic_installer_folder, config.cache_max_size)));
+        // a failed check must not keep the player from installing
+        let update = match config.update_url.clone().map(|url| check_update(&url)) {
+            Some(Ok(update)) => update,
+            Some(Err(error)) => {
+                config.log(format!("Couldn't check for updates: {}", error).as_str());
+                None
+            }
+            None => None,
+        };
+        if let Some(release) = &update {
+            config.log(format!("update available: {}", release.version).as_str());
+        }
         Ok(Display {
             terminal_width: terminal::size()?.0,
             terminal_height: terminal::size()?.1,
-            config
+            config,
+            java_installs,
+            pack: None,
+            cache,
+            from,
+            update,
+            assume_yes,
         })
     }
 
@@ -70,86 +152,675 @@ impl Display {
         Ok(())
     }
 
+    /// The pack chosen in the pack menu.
+    fn current_pack(&self) -> &Pack {
+        &self.config.packs[self.pack.unwrap_or_default()]
+    }
+
+    // PACK MENU
+    /// Lets the player pick the modpack the main menu acts on, with the install state of each pack.
+    /// Skipped when the config only declares one pack.
+    pub fn pack_menu(&mut self) -> crossterm::Result<AppStatus> {
+        self.pack = None;
+        if self.config.packs.len() == 1 {
+            self.pack = Some(0);
+            return Ok(AppStatus::Loop);
+        }
+
+        let labels: Vec<String> = self.config.packs.iter().map(|pack| {
+            let state = if pack.is_installed(&self.config.magic_installer_folder) {"installé"} else {"non installé"}; //lang
+            format!("{} ({})", pack.name, state)
+        }).collect();
+        let mut options: Vec<&str> = labels.iter().map(String::as_str).collect();
+        options.push("Quitter (esc)"); //lang
+
+        match self.select_option(&options, 0)? {
+            Some(index) if index < self.config.packs.len() => {
+                self.pack = Some(index);
+                let pack_id = self.current_pack().id.clone();
+                self.config.log(format!("selected pack: {}", pack_id).as_str());
+                Ok(AppStatus::Loop)
+            }
+            _ => Ok(AppStatus::Exit),
+        }
+    }
+
     // MAIN MENU
     pub fn main_menu(&mut self) -> crossterm::Result<AppStatus> {
-        let options = MAIN_MENU_OPTIONS;
+        // an imported modpack decides its modloader, recorded when it was installed
+        let mut pack = self.current_pack().clone();
+        if let Some(record) = InstallRecord::load(&self.config.magic_installer_folder, &pack.id) {
+            pack.apply_record(&record);
+        }
+        let several_packs = self.config.packs.len() > 1;
+
+        let install_label = match &self.from {
+            Some(from) => format!("Installer le modpack depuis {}", from.file_name().unwrap_or_default().to_string_lossy()), //lang
+            None => "Installer le modpack".to_string(), //lang
+        };
+        let mut actions: Vec<(String, MenuAction)> = vec![
+            (install_label, MenuAction::InstallPack),
+            ("Installer depuis un fichier".to_string(), MenuAction::InstallFromFile), //lang
+            ("Prévisualiser l'installation".to_string(), MenuAction::Preview), //lang
+            (format!("Installer {}", pack.modloader_kind.label()), MenuAction::InstallModloader), //lang
+            ("Supprimer les fichiers du modpack".to_string(), MenuAction::RemoveFiles), //lang
+            (format!("Vider le cache ({})", format_size(self.cache.lock().unwrap().size())), MenuAction::ClearCache), //lang
+        ];
+        if let Some(release) = &self.update {
+            actions.push((format!("Mettre à jour l'installateur ({})", release.version), MenuAction::SelfUpdate)); //lang
+        }
+        if several_packs {
+            actions.push(("Changer de modpack".to_string(), MenuAction::ChangePack)); //lang
+        }
+        actions.push(("Quitter (esc)".to_string(), MenuAction::Quit)); //lang
+        let options: Vec<&str> = actions.iter().map(|(label, _)| label.as_str()).collect();
+        // the menu starts on the preview, so a stray Enter never installs nor deletes anything
+        let preview = actions.iter().position(|(_, action)| matches!(action, MenuAction::Preview)).unwrap_or_default();
+
+        let action = match self.select_option(&options, preview)? {
+            Some(index) => actions[index].1,
+            None if several_packs => MenuAction::ChangePack,
+            None => MenuAction::Quit,
+        };
+
+        match action {
+            MenuAction::InstallPack => {
+                let from = self.from.clone();
+                self.install_pack(&pack, from.as_deref())?;
+            }
+            MenuAction::InstallFromFile => {
+                if let Some(from) = self.file_browser_page()? {
+                    self.install_pack(&pack, Some(&from))?;
+                }
+            }
+            MenuAction::Preview => {
+                let from = self.from.clone();
+                self.preview_page(&pack, from.as_deref())?;
+            }
+            MenuAction::InstallModloader => {
+                self.install_modloader(&pack)?;
+            }
+            MenuAction::RemoveFiles => {
+                let removed = removed_files(&self.config, &pack);
+                if !self.confirm_page(&format!("Supprimer les fichiers de {} ?", pack.name), &removed)? { //lang
+                    return Ok(AppStatus::Loop);
+                }
+                let record = InstallRecord::load(&self.config.magic_installer_folder, &pack.id);
+                if wipes_folders(&pack, record.as_ref()) {
+                    self.remove_files_page(&pack.instance_folder, FILES_TO_REMOVE)?;
+                } else if let Some(record) = record {
+                    record.remove_files(&pack.instance_folder).unwrap_or_log(&mut self.config.debugfile);
+                }
+                InstallRecord::remove(&self.config.magic_installer_folder, &pack.id).unwrap_or_log(&mut self.config.debugfile);
+            }
+            MenuAction::ClearCache => {
+                let cache = (CACHE_FOLDER.to_string(), self.cache.lock().unwrap().size());
+                if self.confirm_page("Vider le cache ?", &[cache])? { //lang
+                    self.clear_cache_page()?;
+                }
+            }
+            MenuAction::SelfUpdate => {
+                if let Some(release) = self.update.clone() {
+                    if self.self_update_page(&release)? {
+                        return Ok(AppStatus::Restart);
+                    }
+                }
+            }
+            MenuAction::ChangePack => {return Ok(AppStatus::Back)}
+            MenuAction::Quit => {return Ok(AppStatus::Exit)}
+        };
+        Ok(AppStatus::Loop)
+    }
+
+    /// Draws the menu with `options`, `selected` being highlighted first, and waits for the player to pick one.
+    /// Returns `None` when Esc is pressed.
+    fn select_option(&mut self, options: &[&str], selected: usize) -> crossterm::Result<Option<usize>> {
         let options_len = options.len();
+        let mut selected = selected;
 
-        let mut selected = 0;
-        let key_pressed: KeyCode;
-        
         // Main drawing
         self.draw_main_menu(selected, options)?;
 
         // Event loop
         loop {
             if event::poll(Duration::from_millis(100))? {
-                if let Event::Key(KeyEvent {code, ..}) = event::read().unwrap() {
-                    match code {
+                match event::read().unwrap() {
+                    Event::Key(KeyEvent {code, kind: KeyEventKind::Press, ..}) => match code {
                         KeyCode::Up => {
-                            selected = (selected-1)%options_len;
+                            selected = (selected+options_len-1)%options_len;
                             self.draw_main_options(selected, options)?;
                         }
                         KeyCode::Down => {
                             selected = (selected+1)%options_len;
                             self.draw_main_options(selected, options)?;
                         }
-                        KeyCode::Enter => {key_pressed = KeyCode::Enter; break}
-                        KeyCode::Esc => {key_pressed = KeyCode::Esc; break}
+                        KeyCode::Enter => {return Ok(Some(selected))}
+                        KeyCode::Esc => {return Ok(None)}
                         _ => {}
+                    },
+                    Event::Resize(width, height) => {
+                        self.terminal_width = width;
+                        self.terminal_height = height;
+                        self.draw_main_menu(selected, options)?;
                     }
+                    _ => {}
+                }
+            }
+            execute!(io::stdout(), cursor::Hide)?;
+        }
+    }
+
+    /// Installs a pack described by a JSON manifest or by a packwiz `pack.toml`.
+    fn install_manifest(&mut self, pack: &Pack) -> crossterm::Result<()> {
+        self.config.log(format!("manifest url: {}", &pack.modpack_url).as_str());
+        let signatures = self.config.require_signatures.then(|| pack.modpack_signatures());
+        let manifest = match pack.format {
+            PackFormat::Packwiz => read_packwiz(&pack.modpack_url, signatures.as_deref()),
+            _ => Manifest::fetch(&pack.modpack_url, signatures.as_deref()),
+        };
+        let manifest = match manifest {
+            Ok(manifest) => manifest,
+            Err(error) if is_signature_error(&error) => {
+                self.config.log(format!("Error: signature of {}: {}", &pack.modpack_url, error).as_str());
+                return self.signature_page(&error.to_string());
+            }
+            Err(error) => {
+                self.config.log(format!("Error: {}", error).as_str());
+                self.error_page(&format!("Impossible de lire le manifeste: {}", error))?; //lang
+                return Ok(());
+            }
+        };
+        self.install_files(pack, &manifest, None)
+    }
+
+    /// Installs the modpack with the steps of its format. `from` is a local archive or folder used instead of
+    /// downloading the modpack, which goes through the same checks, extraction and install record.
+    fn install_pack(&mut self, pack: &Pack, from: Option<&Path>) -> crossterm::Result<()> {
+        let is_mrpack = from.is_some_and(|from| from.extension().is_some_and(|extension| extension == "mrpack"));
+        let format = if is_mrpack {PackFormat::Mrpack} else {pack.format};
+        if self.config.require_signatures && from.is_some_and(Path::is_dir) {
+            return self.signature_page("un dossier ne peut pas être authentifié, utilisez l'archive signée"); //lang
+        }
+        match format {
+            PackFormat::Zip => self.install_zip(pack, from),
+            PackFormat::Manifest | PackFormat::Packwiz if from.is_some() => {
+                self.error_page("Ce modpack ne peut pas être installé depuis un fichier") //lang
+            }
+            PackFormat::Manifest | PackFormat::Packwiz => self.install_manifest(pack),
+            PackFormat::Mrpack => self.install_archive(pack, from, MRPACK_FILE, read_mrpack),
+            PackFormat::CurseForge => {
+                let (api_url, api_key) = (pack.curseforge_api_url.clone(), pack.curseforge_api_key.clone());
+                self.install_archive(pack, from, CURSEFORGE_FILE, |folder| read_curseforge(folder, &api_url, api_key.as_deref()))
+            }
+        }
+    }
+
+    /// Replaces the `mods` and `config` folders with the content of the modpack zip, or of the folder `from`.
+    /// The archive is fetched and checked next to the installer first, so a refused download leaves the instance as it was.
+    fn install_zip(&mut self, pack: &Pack, from: Option<&Path>) -> crossterm::Result<()> {
+        let filename: &str = "modpack.zip";
+        let filepath: String = format!("{}{}", &self.config.magic_installer_folder, filename);
+        let folders: &[&str] = FILES_TO_REMOVE;
+
+        self.config.log(format!("modpack zip file path: {}", &filepath).as_str());
+        self.config.log(format!("files to remove path: {:?}", &folders).as_str());
+
+        // a reinstall wipes the folders, mods the player added included
+        let removed = removed_folders(pack);
+        if !removed.is_empty() && !self.confirm_page(&format!("Réinstaller {} ?", pack.name), &removed)? { //lang
+            return Ok(());
+        }
+        create_folder(&pack.instance_folder);
+        let source = match from {
+            Some(from) if from.is_dir() => {
+                self.config.log(format!("copying modpack folder: {}", from.display()).as_str());
+                if !self.check_content_page(from, &pack.extract_policy)? {
+                    return Ok(());
+                }
+                self.remove_files_page(&pack.instance_folder, folders)?;
+                if !self.copy_folder_page(from, &pack.instance_folder, &pack.extract_policy)? {
+                    return Ok(());
+                }
+                from.display().to_string()
+            }
+            _ => {
+                let source = match self.fetch_archive(pack, from, &filepath)? {
+                    Some(source) => source,
+                    None => return Ok(()),
+                };
+                // a corrupted archive or one over the limits is refused before the old files are wiped
+                if !self.check_content_page(Path::new(&filepath), &pack.extract_policy)? {
+                    remove_file(&filepath).unwrap_or_log(&mut self.config.debugfile);
+                    return Ok(());
+                }
+                self.remove_files_page(&pack.instance_folder, folders)?;
+                let extracted = self.unzip_page(&filepath, &pack.instance_folder, &pack.extract_policy)?;
+                remove_file(&filepath).unwrap_or_log(&mut self.config.debugfile);
+                if !extracted {
+                    return Ok(());
+                }
+                source
+            }
+        };
+        let record = InstallRecord { pack: pack.id.clone(), source: Some(source), ..Default::default() };
+        record.save(&self.config.magic_installer_folder).unwrap_or_log(&mut self.config.debugfile);
+        Ok(())
+    }
+
+    /// Puts the modpack archive at `filepath`: copied from `from` or from a local `modpack_url`, else downloaded
+    /// (or taken from the cache), then checked against `modpack_hash`.
+    /// Returns where the archive came from, `None` if it couldn't be read or didn't match its hashes.
+    fn fetch_archive(&mut self, pack: &Pack, from: Option<&Path>, filepath: &str) -> crossterm::Result<Option<String>> {
+        let local: Option<PathBuf> = match from {
+            Some(from) => Some(from.to_path_buf()),
+            None if !is_url(&pack.modpack_url) => Some(PathBuf::from(get_env_path(&pack.modpack_url))),
+            None => None,
+        };
+        // a configured signature url is the one of the configured modpack, not of another archive
+        let signatures = match from {
+            Some(from) => vec![signature_url(&from.display().to_string())],
+            None => pack.modpack_signatures(),
+        };
+        let source = match local {
+            Some(local) => {
+                if let Err(error) = fs::copy(&local, filepath) {
+                    self.config.log(format!("Error: {}: {}", local.display(), error).as_str());
+                    self.error_page(&format!("Impossible de lire {}: {}", local.display(), error))?; //lang
+                    return Ok(None);
+                }
+                if !self.check_download(filepath, &pack.modpack_hashes)? {
+                    return Ok(None);
+                }
+                if !self.check_signature(filepath, &signatures)? {
+                    return Ok(None);
+                }
+                local.display().to_string()
+            }
+            None => {
+                let urls = pack.modpack_urls();
+                match self.cached_download(filepath, &urls, &pack.modpack_hashes)? {
+                    Some(source) if self.check_signature(filepath, &signatures)? => source,
+                    _ => return Ok(None),
+                }
+            }
+        };
+        self.config.log(format!("modpack taken from: {}", &source).as_str());
+        Ok(Some(source))
+    }
+
+    /// Installs a modpack exported by another launcher (Modrinth, CurseForge): the archive is downloaded or copied
+    /// from a local path, extracted next to the installer as `filename`, then `read` turns it into a manifest.
+    /// `from` can also be the folder of an already extracted archive.
+    fn install_archive(&mut self, pack: &Pack, from: Option<&Path>, filename: &str, read: impl FnOnce(&Path) -> io::Result<Manifest>) -> crossterm::Result<()> {
+        let folder: String = format!("{}{}{}\\", &self.config.magic_installer_folder, IMPORTS_FOLDER, &pack.id);
+        let filepath: String = format!("{}{}", &folder, filename);
+        self.config.log(format!("imported modpack path: {}", &filepath).as_str());
+
+        let _ = remove_dir_all(&folder);
+        create_folder(&folder);
+        // the archive only lands in the imports folder, the allow-list is checked when its overrides are copied
+        let policy = pack.extract_policy.without_allow_list();
+        let source = match from {
+            Some(from) if from.is_dir() => {
+                if !self.copy_folder_page(from, &folder, &policy)? {
+                    return Ok(());
+                }
+                from.display().to_string()
+            }
+            _ => {
+                let source = match self.fetch_archive(pack, from, &filepath)? {
+                    Some(source) => source,
+                    None => return Ok(()),
+                };
+                if !self.unzip_page(&filepath, &folder, &policy)? {
+                    remove_dir_all(&folder).unwrap_or_log(&mut self.config.debugfile);
+                    return Ok(());
+                }
+                source
+            }
+        };
+
+        match read(Path::new(&folder)) {
+            Ok(manifest) => {
+                self.config.log(format!("imported modloader: {:?}, minecraft {:?}", &manifest.modloader, &manifest.minecraft_version).as_str());
+                self.install_files(pack, &manifest, Some(source))?;
+            }
+            Err(error) => {
+                self.config.log(format!("Error: {}", error).as_str());
+                self.error_page(&format!("Impossible de lire le modpack: {}", error))?; //lang
+            }
+        }
+        remove_dir_all(&folder).unwrap_or_log(&mut self.config.debugfile);
+        Ok(())
+    }
+
+    /// Installs the files of a manifest: the player picks the optional files, then the selected files are downloaded
+    /// and the ones a previous install left but which are no longer wanted are removed.
+    /// `source` is where an imported archive came from, kept in the install record.
+    fn install_files(&mut self, pack: &Pack, manifest: &Manifest, source: Option<String>) -> crossterm::Result<()> {
+        // the previous selection is kept on updates, the manifest defaults are used on the first install
+        let record = InstallRecord::load(&self.config.magic_installer_folder, &pack.id);
+        let optional_files = manifest.optional_files();
+        let mut selected: Vec<String> = manifest.initial_selection(record.as_ref());
+        if !optional_files.is_empty() {
+            selected = match self.optional_files_page(&optional_files, selected)? {
+                Some(selected) => selected,
+                None => return Ok(()),
+            };
+        }
+        self.config.log(format!("selected optional files: {:?}", &selected).as_str());
+
+        // files outside of the allowed folders are left out like the entries of an archive
+        let mut rejected: Vec<Rejected> = Vec::new();
+        let files: Vec<&ManifestFile> = manifest.selected_files(&selected).into_iter()
+            .filter(|file| match pack.extract_policy.check_path(&file.relative_path()) {
+                Some(reason) => {
+                    rejected.push(Rejected { path: file.relative_path(), reason });
+                    false
+                }
+                None => true,
+            })
+            .collect();
+        if self.config.require_signatures {
+            if let Err(error) = Manifest::check_hashes(&files) {
+                self.config.log(format!("Error: {}", error).as_str());
+                return self.signature_page(&error.to_string());
+            }
+        }
+        // files to merge are downloaded next to the player's file, then merged into it.
+        // only the files the modpack owns are recorded, so nothing removes the player's own files
+        let mut downloads: Vec<(&ManifestFile, PathBuf)> = Vec::new();
+        let mut merges: Vec<(PathBuf, PathBuf)> = Vec::new();
+        let mut paths: Vec<String> = Vec::new();
+        for file in &files {
+            let target = file.target(&pack.instance_folder).unwrap_or_log(&mut self.config.debugfile);
+            let action = file.action(&target);
+            if file.is_recorded(action, record.as_ref()) {
+                paths.push(file.relative_path());
+            }
+            match action {
+                FileAction::Keep => {
+                    self.config.log(format!("keeping existing file: {}", target.display()).as_str());
+                }
+                FileAction::UpToDate => {
+                    self.config.log(format!("file up to date: {}", target.display()).as_str());
+                }
+                FileAction::Merge => {
+                    let defaults = PathBuf::from(format!("{}.defaults", target.display()));
+                    downloads.push((file, defaults.clone()));
+                    merges.push((target, defaults));
                 }
-                if let Event::Resize(width, height) = event::read().unwrap() {
+                FileAction::Download => downloads.push((file, target)),
+            }
+        }
+        for overrides in &manifest.overrides {
+            list_files(overrides).unwrap_or_log(&mut self.config.debugfile).into_iter().for_each(|path| {
+                if !paths.contains(&path) && pack.extract_policy.check_path(&path).is_none() {
+                    paths.push(path);
+                }
+            });
+        }
+        // the files of the previous install no longer wanted are deleted once the new ones are in place
+        let stale = InstallRecord {
+            files: record.iter().flat_map(|record| &record.files).filter(|path| !paths.contains(path)).cloned().collect(),
+            ..Default::default()
+        };
+        let removed: Vec<(String, u64)> = stale.files.iter()
+            .filter_map(|path| fs::metadata(Path::new(&pack.instance_folder).join(path)).ok().map(|metadata| (path.clone(), metadata.len())))
+            .collect();
+        if !removed.is_empty() && !self.confirm_page(&format!("Mettre à jour {} ?", pack.name), &removed)? { //lang
+            return Ok(());
+        }
+
+        create_folder(&pack.instance_folder);
+        // a failed download leaves the previous install and its record as they were
+        if !self.files_download_page(&downloads)? {
+            return Ok(());
+        }
+        for (target, defaults) in merges {
+            self.config.log(format!("merging keys into: {}", target.display()).as_str());
+            merge_keys(&target, &defaults).unwrap_or_log(&mut self.config.debugfile);
+            remove_file(&defaults).unwrap_or_log(&mut self.config.debugfile);
+        }
+        for overrides in &manifest.overrides {
+            self.config.log(format!("copying overrides: {}", overrides.display()).as_str());
+            match copy_folder_checked(overrides, Path::new(&pack.instance_folder), &pack.extract_policy) {
+                Ok(overrides_rejected) => rejected.extend(overrides_rejected),
+                Err(error) => {
+                    self.config.log(format!("Error: copying overrides: {}", error).as_str());
+                    return self.error_page(&format!("Impossible de copier les fichiers du modpack: {}", error)); //lang
+                }
+            }
+        }
+        if !stale.files.is_empty() {
+            self.config.log(format!("removing stale files: {:?}", &stale.files).as_str());
+            stale.remove_files(&pack.instance_folder).unwrap_or_log(&mut self.config.debugfile);
+        }
+        self.rejected_page(&rejected)?;
+
+        let resource_packs: Vec<String> = files.iter().filter_map(|file| file.enabled_resource_pack()).collect();
+        if !resource_packs.is_empty() {
+            self.config.log(format!("enabling resource packs: {:?}", &resource_packs).as_str());
+            enable_resource_packs(&pack.instance_folder, &resource_packs).unwrap_or_log(&mut self.config.debugfile);
+        }
+
+        let record = InstallRecord {
+            pack: pack.id.clone(),
+            files: paths,
+            optional: selected,
+            offered: Some(optional_files.iter().map(|file| file.relative_path()).collect()),
+            source,
+            minecraft_version: manifest.minecraft_version.clone(),
+            modloader: manifest.modloader.as_ref().map(|(kind, _)| *kind),
+            modloader_version: manifest.modloader.as_ref().map(|(_, version)| version.clone()),
+        };
+        record.save(&self.config.magic_installer_folder).unwrap_or_log(&mut self.config.debugfile);
+        Ok(())
+    }
+
+    /// Toggle list of the optional files, Space toggles the highlighted file.
+    /// Returns the paths of the selected files, or `None` if the player cancelled with Esc.
+    fn optional_files_page(&mut self, files: &[&ManifestFile], selected: Vec<String>) -> crossterm::Result<Option<Vec<String>>> {
+        let mut checked: Vec<bool> = files.iter().map(|file| selected.contains(&file.relative_path())).collect();
+        let mut highlighted = 0;
+        self.draw_optional_files(files, &checked, highlighted)?;
+
+        loop {
+            if event::poll(Duration::from_millis(100))? {
+                match event::read().unwrap() {
+                    Event::Key(KeyEvent {code, kind: KeyEventKind::Press, ..}) => match code {
+                        KeyCode::Up => highlighted = (highlighted+files.len()-1)%files.len(),
+                        KeyCode::Down => highlighted = (highlighted+1)%files.len(),
+                        KeyCode::Char(' ') => checked[highlighted] = !checked[highlighted],
+                        KeyCode::Enter => break,
+                        KeyCode::Esc => return Ok(None),
+                        _ => continue,
+                    },
+                    Event::Resize(width, height) => {
+                        self.terminal_width = width;
+                        self.terminal_height = height;
+                    }
+                    _ => continue,
+                }
+                self.draw_optional_files(files, &checked, highlighted)?;
+            }
+        }
+
+        Ok(Some(files.iter().zip(checked).filter(|(_, checked)| *checked).map(|(file, _)| file.relative_path()).collect()))
+    }
+
+    fn draw_optional_files(&self, files: &[&ManifestFile], checked: &[bool], highlighted: usize) -> crossterm::Result<()> {
+        let mut stdout = io::stdout();
+        execute!(stdout, terminal::Clear(terminal::ClearType::All), cursor::Hide, cursor::MoveTo(0, 2))?;
+        self.write_stylized_centered("Mods optionnels".with(Color::Blue).attribute(Attribute::Bold))?; //lang
+        execute!(stdout, cursor::MoveTo(0, 4))?;
+        self.write_stylized_centered("↑ ↓ pour naviguer, Espace pour cocher, Entrée pour valider, Esc pour annuler".with(Color::DarkGrey).attribute(Attribute::Dim))?; //lang
+
+        // each file takes 3 lines, only the files fitting in the terminal are drawn, scrolling with the highlighted one
+        let visible = (self.terminal_height.saturating_sub(8) as usize / 3).max(1);
+        let offset = highlighted.saturating_sub(visible - 1);
+        files.iter().enumerate().skip(offset).take(visible).for_each(|(index, file)| {
+            let checkbox = if checked[index] {"[x]"} else {"[ ]"};
+            let line = format!("{} {}", checkbox, file.display_name());
+            let row = 7 + 3*(index - offset) as u16;
+            execute!(stdout, cursor::MoveTo(0, row)).unwrap();
+            if index == highlighted {
+                self.write_stylized_centered(format!("> {} <", line).as_str().with(Color::Green).attribute(Attribute::Bold)).unwrap();
+            } else {
+                self.write_centered(&line).unwrap();
+            }
+            execute!(stdout, cursor::MoveTo(0, row + 1)).unwrap();
+            self.write_stylized_centered(file.description.as_str().with(Color::DarkGrey)).unwrap();
+        });
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Lets the player pick a modpack archive, or an extracted modpack folder, starting in the current folder.
+    /// Returns `None` if the player cancelled with Esc.
+    fn file_browser_page(&mut self) -> crossterm::Result<Option<PathBuf>> {
+        let mut folder: PathBuf = env::current_dir()?;
+        let mut highlighted = 0;
+        loop {
+            let entries = Display::browser_entries(&folder);
+            self.draw_file_browser(&folder, &entries, highlighted)?;
+            match event::read()? {
+                Event::Key(KeyEvent {code, kind: KeyEventKind::Press, ..}) => match code {
+                    KeyCode::Up => highlighted = (highlighted+entries.len()-1)%entries.len(),
+                    KeyCode::Down => highlighted = (highlighted+1)%entries.len(),
+                    KeyCode::Enter => match &entries[highlighted] {
+                        BrowserEntry::CurrentFolder => return Ok(Some(folder)),
+                        BrowserEntry::Folder(path) => {
+                            folder = path.clone();
+                            highlighted = 0;
+                        }
+                        BrowserEntry::Archive(path) => return Ok(Some(path.clone())),
+                    },
+                    KeyCode::Esc => return Ok(None),
+                    _ => {}
+                },
+                Event::Resize(width, height) => {
                     self.terminal_width = width;
                     self.terminal_height = height;
-                    self.draw_main_menu(selected, options)?;
                 }
+                _ => {}
             }
-            execute!(io::stdout(), cursor::Hide)?;
         }
+    }
 
-        match key_pressed {
-            KeyCode::Esc => {return Ok(AppStatus::Exit)}
-            _ => {match selected {
-                0 => { // install the modpack
-                    let filename: &str = "modpack.zip";
-                    let filepath: String = format!("{}{}", &self.config.minecraft_folder, filename);
-                    let folders: &[&str] = FILES_TO_REMOVE;
-
-                    self.config.log(format!("modpack zip file path: {}", &filepath).as_str());
-                    self.config.log(format!("files to remove path: {:?}", &folders).as_str());
-                    
-                    self.remove_files_page(&self.config.minecraft_folder, folders)?;
-                    self.download_page(&filepath, &self.config.modpack_url).unwrap_or_log(&mut self.config.debugfile);
-                    self.unzip_page(&filename, &self.config.minecraft_folder).unwrap_or_log(&mut self.config.debugfile);
-                }
-                1 => { // install the modloader (fabric/forge)
-                    let filename: &str = "modloader.zip";
-                    let filepath: String = format!("{}{}", &self.config.magic_installer_folder, filename);
-                    let executable_path: String = format!("{}{}", &self.config.magic_installer_folder, self.config.modloader_execname);
-                    
-                    self.config.log(format!("modloader zip path: {}", &filepath).as_str());
-                    self.config.log(format!("modloader exec path: {}", &executable_path).as_str());
-                    self.config.log(format!("magic_installer folder path: {}", &self.config.magic_installer_folder).as_str());
-
-                    self.download_page(&filepath, &self.config.modloader_url).unwrap_or_log(&mut self.config.debugfile);
-                    self.unzip_page(&filename, &self.config.magic_installer_folder).unwrap_or_log(&mut self.config.debugfile);
-                    self.executable_page(&executable_path).unwrap_or_log(&mut self.config.debugfile);
-                }
-                2 => { // remove all files
-                    let folders = FILES_TO_REMOVE;
-                    self.remove_files_page(&self.config.minecraft_folder, folders)?;
-                } // exit
-                3 => {return Ok(AppStatus::Exit)}
-                _ => {}
-            }},
-        };
-        Ok(AppStatus::Loop)
+    /// The folder itself, its parent, its subfolders then its archives, sorted by name.
+    fn browser_entries(folder: &Path) -> Vec<BrowserEntry> {
+        let mut entries: Vec<BrowserEntry> = vec![BrowserEntry::CurrentFolder];
+        if let Some(parent) = folder.parent() {
+            entries.push(BrowserEntry::Folder(parent.to_path_buf()));
+        }
+        let mut paths: Vec<PathBuf> = fs::read_dir(folder).map(|read_dir| read_dir.flatten().map(|entry| entry.path()).collect()).unwrap_or_default();
+        paths.sort();
+        paths.iter().filter(|path| path.is_dir()).for_each(|path| entries.push(BrowserEntry::Folder(path.clone())));
+        paths.iter()
+            .filter(|path| path.is_file() && path.extension().is_some_and(|extension| ARCHIVE_EXTENSIONS.contains(&extension.to_string_lossy().to_lowercase().as_str())))
+            .for_each(|path| entries.push(BrowserEntry::Archive(path.clone())));
+        entries
+    }
+
+    fn draw_file_browser(&self, folder: &Path, entries: &[BrowserEntry], highlighted: usize) -> crossterm::Result<()> {
+        let mut stdout = io::stdout();
+        execute!(stdout, terminal::Clear(terminal::ClearType::All), cursor::Hide, cursor::MoveTo(0, 2))?;
+        self.write_stylized_centered("Installer depuis un fichier".with(Color::Blue).attribute(Attribute::Bold))?; //lang
+        execute!(stdout, cursor::MoveTo(0, 4))?;
+        self.write_centered(&folder.display().to_string())?;
+        execute!(stdout, cursor::MoveTo(0, 5))?;
+        self.write_stylized_centered("↑ ↓ pour naviguer, Entrée pour ouvrir ou choisir, Esc pour annuler".with(Color::DarkGrey).attribute(Attribute::Dim))?; //lang
+
+        // only the lines fitting in the terminal are drawn, scrolling with the highlighted entry
+        let visible = (self.terminal_height.saturating_sub(9) as usize).max(1);
+        let offset = highlighted.saturating_sub(visible - 1);
+        entries.iter().enumerate().skip(offset).take(visible).for_each(|(index, entry)| {
+            let name = |path: &PathBuf| path.file_name().map_or(path.display().to_string(), |name| name.to_string_lossy().to_string());
+            let line = match entry {
+                BrowserEntry::CurrentFolder => "[Choisir ce dossier]".to_string(), //lang
+                BrowserEntry::Folder(path) if Some(path.as_path()) == folder.parent() => "..".to_string(),
+                BrowserEntry::Folder(path) => format!("{}/", name(path)),
+                BrowserEntry::Archive(path) => name(path),
+            };
+            execute!(stdout, cursor::MoveTo(0, 7 + (index - offset) as u16)).unwrap();
+            if index == highlighted {
+                self.write_stylized_centered(format!("> {} <", line).as_str().with(Color::Green).attribute(Attribute::Bold)).unwrap();
+            } else {
+                self.write_centered(&line).unwrap();
+            }
+        });
+        stdout.flush()?;
+        Ok(())
+    }
+
+    /// Installs the modloader with the strategy matching its kind, then checks its version folder exists.
+    fn install_modloader(&mut self, pack: &Pack) -> crossterm::Result<()> {
+        let strategy = install_strategy(pack, &self.config.minecraft_folder);
+        self.config.log(format!("modloader kind: {:?}", pack.modloader_kind).as_str());
+        self.config.log(format!("modloader install strategy: {:?}", &strategy).as_str());
+
+        let mut version_id: Option<String> = expected_version_id(pack);
+        match strategy {
+            Some(InstallStrategy::Profile { meta_url, minecraft_version, loader_version }) => {
+                let installed_id = match self.modloader_page(pack.modloader_kind, &meta_url, &minecraft_version, &loader_version)? {
+                    Some(installed_id) => installed_id,
+                    None => return Ok(()),
+                };
+                version_id = version_id.or(Some(installed_id));
+            }
+            Some(InstallStrategy::Installer { urls, hashes, filename, archive, args }) => {
+                let download_name: &str = if archive {"modloader.zip"} else {&filename};
+                let filepath: String = format!("{}{}", &self.config.magic_installer_folder, download_name);
+                let executable_path: String = format!("{}{}", &self.config.magic_installer_folder, filename);
+
+                self.config.log(format!("modloader download path: {}", &filepath).as_str());
+                self.config.log(format!("modloader exec path: {}", &executable_path).as_str());
+                self.config.log(format!("magic_installer folder path: {}", &self.config.magic_installer_folder).as_str());
+
+                let source = match self.cached_download(&filepath, &urls, &hashes)? {
+                    Some(source) => source,
+                    None => return Ok(()),
+                };
+                self.config.log(format!("modloader downloaded from: {}", &source).as_str());
+                // the installer gets executed, a configured url could have been hijacked
+                if pack.modloader_url.is_some() && !self.check_signature(&filepath, &pack.modloader_signatures(&urls))? {
+                    return Ok(());
+                }
+                if archive {
+                    let folder = self.config.magic_installer_folder.clone();
+                    if !self.unzip_page(&filepath, &folder, &pack.extract_policy.without_allow_list())? {
+                        return Ok(());
+                    }
+                }
+                if !self.executable_page(pack, &executable_path, &args)? {
+                    return Ok(());
+                }
+            }
+            None => {
+                self.error_page("Aucun modloader configuré")?; //lang
+                return Ok(());
+            }
+        }
+
+        if let Some(version_id) = version_id {
+            let installed = is_version_installed(&self.config.minecraft_folder, &version_id);
+            self.config.log(format!("modloader version {} installed: {}", &version_id, installed).as_str());
+            if !installed {
+                self.error_page(&format!("Version {} introuvable dans .minecraft/versions", version_id))?; //lang
+                return Ok(());
+            }
+            self.profile_page(pack, &version_id)?;
+        }
+        Ok(())
     }
 
     fn draw_main_menu(&self, selected: usize, options: &[&str]) -> crossterm::Result<()>{
         let title: &str = MAIN_TITLE;
-        let author: String = format!("{} - {}", AUTHOR, VERSION);
+        let author: String = match &self.update {
+            Some(release) => format!("{} - {} ({} disponible)", AUTHOR, VERSION, release.version), //lang
+            None => format!("{} - {}", AUTHOR, VERSION),
+        };
         let bottom_text: &str = BOTTOM_TEXT;
         let controls: &str = CONTROLS;
 
@@ -166,8 +837,16 @@ impl Display {
 
         execute!(stdout, cursor::MoveTo(0, 15))?;
         self.write_stylized_centered(author.as_str().with(Color::Blue).attribute(Attribute::Dim))?;
+        if self.pack.is_some() && self.config.packs.len() > 1 {
+            execute!(stdout, cursor::MoveTo(0, 16))?;
+            self.write_stylized_centered(self.current_pack().name.as_str().with(Color::Green).attribute(Attribute::Bold))?;
+        }
         execute!(stdout, cursor::MoveTo(0, 17))?;
         self.write_stylized_centered(controls.with(Color::DarkGrey).attribute(Attribute::Dim))?;
+        if self.pack.is_some() {
+            execute!(stdout, cursor::MoveTo(0, 18))?;
+            self.draw_java_status()?;
+        }
         execute!(stdout, cursor::MoveTo(0, self.terminal_height))?;
         self.write_stylized_centered(bottom_text.with(Color::DarkGrey).attribute(Attribute::Dim))?;
 
@@ -175,6 +854,19 @@ impl Display {
         Ok(())
     }
 
+    fn draw_java_status(&self) -> crossterm::Result<()> {
+        let required_version = self.current_pack().java_version;
+        let (status, color) = match (select_java(&self.java_installs, required_version), required_version) {
+            (JavaStatus::Found(install), Some(required)) => (format!("Java {} requis : Java {} détecté", required.label(), install.version), Color::Green), //lang
+            (JavaStatus::Found(install), None) => (format!("Java {} détecté", install.version), Color::DarkGrey), //lang
+            (JavaStatus::WrongVersion(install), Some(required)) => (format!("Java {} requis : seul Java {} est installé, incompatible", required.label(), install.major), Color::Red), //lang
+            (JavaStatus::WrongVersion(install), None) => (format!("Java {} détecté", install.version), Color::DarkGrey), //lang
+            (JavaStatus::Missing, Some(required)) => (format!("Java {} requis : aucune installation de Java trouvée", required.label()), Color::Red), //lang
+            (JavaStatus::Missing, None) => ("Aucune installation de Java trouvée".to_string(), Color::DarkGrey), //lang
+        };
+        self.write_stylized_centered(status.as_str().with(color))
+    }
+
     pub fn draw_main_options(&self, selected: usize, options: &[&str]) -> crossterm::Result<()> {
         let mut stdout = io::stdout();
         execute!(stdout, cursor::MoveTo(0, 4))?;
@@ -193,10 +885,8 @@ impl Display {
 
 
     // Téléchargement et Installation
-    pub fn download_page(&self, path: &str, url: &str) -> crossterm::Result<()> {
-        let path: Arc<String> = Arc::new(path.to_owned());
-        let url: Arc<String> = Arc::new(url.to_owned());
-
+    /// Downloads the file from the first working url of `urls`, returns the url used.
+    pub fn download_page(&self, path: &str, urls: &[String]) -> crossterm::Result<String> {
         let mut stdout: io::Stdout = io::stdout();
         let height: u16 = (self.terminal_height as f32 / 2.0) as u16;
         execute!(stdout,
@@ -210,20 +900,167 @@ impl Display {
         // execute!(stdout, cursor::MoveTo(0, height*2u16))?;
         // self.write_stylized_centered("Si le télécharchement semble rester à 0%, Ctrl+C peut débloquer le programme".with(Color::DarkGrey))?; //lang
 
+        let source = self.download_progress(path, urls, height)?;
+
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(0, height - 2))?;
+
+        self.write_centered("Téléchargement terminé !")?; //lang
+        sleep(Duration::from_secs(1));
+        Ok(source)
+    }
+
+    /// Downloads a single file unless the cache has it, then checks it against `hashes`.
+    /// Returns the url used, `cache` when the file came from the cache, or `None` if every mirror failed
+    /// or the file didn't match its hashes.
+    fn cached_download(&mut self, path: &str, urls: &[String], hashes: &HashMap<String, String>) -> crossterm::Result<Option<String>> {
+        if !hashes.is_empty() && self.cache.lock().unwrap().fetch(hashes, Path::new(path)).unwrap_or(false) {
+            return Ok(Some(CACHE_FOLDER.to_string()));
+        }
+        let source = match self.download_page(path, urls) {
+            Ok(source) => source,
+            Err(error) => {
+                self.config.log(format!("Couldn't download {}: {}", path, error).as_str());
+                self.error_page(&format!("le téléchargement a échoué ({})", error))?; //lang
+                return Ok(None);
+            }
+        };
+        if !self.check_download(path, hashes)? {
+            return Ok(None);
+        }
+        if !hashes.is_empty() {
+            if let Err(error) = self.cache.lock().unwrap().store(Path::new(path), hashes) {
+                self.config.log(format!("Couldn't cache {}: {}", path, error).as_str());
+            }
+        }
+        Ok(Some(source))
+    }
+
+    /// Checks a downloaded file against the configured hashes, shows an error and deletes the file if they don't match.
+    fn check_download(&mut self, path: &str, hashes: &HashMap<String, String>) -> crossterm::Result<bool> {
+        if let Err(error) = verify_hashes(Path::new(path), hashes) {
+            self.config.log(format!("Error: {}", error).as_str());
+            remove_file(path)?;
+            self.error_page(&error.to_string())?;
+            return Ok(false);
+        }
+        Ok(true)
+    }
+
+    /// Checks `path` against one of the signatures at `signatures` when the config requires signed files.
+    /// Shows the refusal page and deletes the file if the check fails.
+    fn check_signature(&mut self, path: &str, signatures: &[String]) -> crossterm::Result<bool> {
+        if !self.config.require_signatures {
+            return Ok(true);
+        }
+        self.verify_signature(path, signatures)
+    }
+
+    /// Same as `check_signature` whatever the config, for the files that are always signed.
+    fn verify_signature(&mut self, path: &str, signatures: &[String]) -> crossterm::Result<bool> {
+        if let Err(error) = verify_file(Path::new(path), signatures) {
+            self.config.log(format!("Error: signature of {}: {}", path, error).as_str());
+            remove_file(path)?;
+            self.signature_page(&error.to_string())?;
+            return Ok(false);
+        }
+        self.config.log(format!("signature of {} verified", path).as_str());
+        Ok(true)
+    }
+
+    /// Downloads the files of a manifest to their paths, several at a time, showing the progress of the whole batch
+    /// and the files being downloaded.
+    /// Returns whether every file was downloaded, a file that still fails after its retries is shown on the error page.
+    pub fn files_download_page(&mut self, downloads: &[(&ManifestFile, PathBuf)]) -> crossterm::Result<bool> {
+        let mut stdout: io::Stdout = io::stdout();
+        let height: u16 = (self.terminal_height as f32 / 2.0) as u16;
+        let jobs: Vec<DownloadJob> = downloads.iter().map(|(file, target)| DownloadJob {
+            name: file.display_name(),
+            path: target.clone(),
+            urls: file.urls(),
+            hashes: file.hashes.clone(),
+        }).collect();
+        let progress = start_batch(jobs, self.config.download_concurrency, Arc::clone(&self.cache));
+
+        loop {
+            let (finished, error) = {
+                let progress = progress.lock().unwrap();
+                execute!(stdout,
+                    terminal::Clear(terminal::ClearType::All),
+                    cursor::Hide,
+                    cursor::MoveTo(0, height - 2))?;
+                self.write_centered(&format!("Téléchargement {}/{} fichiers", progress.files_done, progress.files_total))?; //lang
+                execute!(stdout, cursor::MoveTo(0, height))?;
+                let percentage = progress.files_done as f32 / progress.files_total.max(1) as f32;
+                self.write_centered(&format!("{} {}%", Display::download_bar(percentage), (percentage*100.0) as u32))?;
+                execute!(stdout, cursor::MoveTo(0, height + 1))?;
+                self.write_stylized_centered(format!("{} - {}/s", format_size(progress.bytes), format_size(progress.rate() as u64)).as_str().with(Color::DarkGrey))?;
+                execute!(stdout, cursor::MoveTo(0, height + 2))?;
+                self.write_stylized_centered(Display::rate_limit_label().as_str().with(Color::DarkGrey).attribute(Attribute::Dim))?;
+                progress.active.iter().enumerate().for_each(|(index, name)| {
+                    execute!(stdout, cursor::MoveTo(0, height + 4 + index as u16)).unwrap();
+                    self.write_stylized_centered(name.as_str().with(Color::DarkGrey).attribute(Attribute::Dim)).unwrap();
+                });
+                (progress.is_finished(), progress.error.clone())
+            };
+            if finished {
+                let (mirrors, cached) = {
+                    let progress = progress.lock().unwrap();
+                    (progress.mirrors.clone(), progress.files_cached)
+                };
+                self.config.log(format!("files taken from the cache: {}", cached).as_str());
+                mirrors.iter().for_each(|(name, url)| self.config.log(format!("{} downloaded from mirror: {}", name, url).as_str()));
+                if let Some(error) = error {
+                    self.config.log(format!("Error: {}", error).as_str());
+                    self.error_page(&error)?;
+                    return Ok(false);
+                }
+                break;
+            }
+            for _ in 0..10 {
+                self.poll_rate_keys()?;
+            }
+        }
+
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::Hide,
+            cursor::MoveTo(0, height - 2))?;
+
+        self.write_centered("Téléchargement terminé !")?; //lang
+        sleep(Duration::from_secs(1));
+        Ok(true)
+    }
+
+    /// Downloads `urls` to `path` in a thread, drawing the progress bar on the line `height`.
+    /// Returns the url the file was downloaded from.
+    fn download_progress(&self, path: &str, urls: &[String], height: u16) -> crossterm::Result<String> {
+        let path: Arc<String> = Arc::new(path.to_owned());
+        let urls: Arc<Vec<String>> = Arc::new(urls.to_vec());
+        let mut stdout: io::Stdout = io::stdout();
+
         let (tx, rx) = mpsc::channel();
 
         let handle = thread::spawn(move || {
-            download_file(&path, &url, tx).expect("Couldn't download file");
+            download_file(&path, &urls, tx).expect("Couldn't download file");
         });
+        execute!(stdout, cursor::MoveTo(0, height + 1))?;
+        self.write_stylized_centered(Display::rate_limit_label().as_str().with(Color::DarkGrey))?;
 
-        loop {
+        let source = loop {
             match rx.try_recv() {
                 Ok(DownloadStatus::Downloading(percentage)) => {
                     execute!(stdout, cursor::MoveTo(0, height))?;
                     self.write_centered(&format!("{} {}%", Display::download_bar(percentage), (percentage*100.0) as u32))?;
                 },
-                Ok(DownloadStatus::Downloaded) => {
-                    break;
+                Ok(DownloadStatus::Mirror(url)) => {
+                    execute!(stdout, cursor::MoveTo(0, height + 3), terminal::Clear(terminal::ClearType::CurrentLine))?;
+                    self.write_stylized_centered(format!("Nouvel essai depuis {}", url).as_str().with(Color::DarkGrey))?; //lang
+                },
+                Ok(DownloadStatus::Downloaded(url)) => {
+                    break url;
                 },
                 Ok(DownloadStatus::Error(error)) => {
                     execute!(stdout, cursor::MoveTo(0, height))?;
@@ -231,21 +1068,43 @@ impl Display {
                     sleep(Duration::from_secs(2));
                     execute!(stdout,terminal::Clear(terminal::ClearType::All))?;
                     execute!(stdout, cursor::MoveTo(0, height))?;
-                    return Err(io::Error::new(io::ErrorKind::Other, "Download Error"));
+                    return Err(io::Error::other(error));
                 } 
-                Err(_) => {}
+                Ok(DownloadStatus::Received(_)) => {}
+                Err(_) => {
+                    if self.poll_rate_keys()? {
+                        execute!(stdout, cursor::MoveTo(0, height + 1), terminal::Clear(terminal::ClearType::CurrentLine))?;
+                        self.write_stylized_centered(Display::rate_limit_label().as_str().with(Color::DarkGrey))?;
+                    }
+                }
             }
-        }
+        };
         handle.join().unwrap();
+        Ok(source)
+    }
 
-        execute!(stdout,
-            terminal::Clear(terminal::ClearType::All),
-            cursor::Hide,
-            cursor::MoveTo(0, height - 2))?;
+    /// Applies the + and - keys pressed during a download to the rate limit, waiting a few milliseconds for them.
+    /// Returns whether the limit changed.
+    fn poll_rate_keys(&self) -> crossterm::Result<bool> {
+        let mut changed = false;
+        while event::poll(Duration::from_millis(10))? {
+            if let Event::Key(KeyEvent {code, kind: KeyEventKind::Press, ..}) = event::read()? {
+                match code {
+                    KeyCode::Char('+') => increase_rate_limit(),
+                    KeyCode::Char('-') => decrease_rate_limit(),
+                    _ => continue,
+                }
+                changed = true;
+            }
+        }
+        Ok(changed)
+    }
 
-        self.write_centered("Téléchargement terminé !")?; //lang
-        sleep(Duration::from_secs(1));
-        Ok(())
+    fn rate_limit_label() -> String {
+        match rate_limit() {
+            0 => "Vitesse illimitée, - pour la limiter".to_string(), //lang
+            rate => format!("Vitesse max : {}/s, + et - pour la régler", format_size(rate)), //lang
+        }
     }
 
     fn download_bar(percentage: f32) -> String {
@@ -263,7 +1122,27 @@ impl Display {
         bar
     }
 
-    pub fn unzip_page(&self, filename: &str, folderpath: &str) -> crossterm::Result<()> {
+    /// Extracts the archive at `archive` into `folderpath` following `policy`, then lists the entries left out.
+    /// Returns whether the archive was extracted, an error page being shown otherwise.
+    /// Checks the archive or folder `content` against `policy` before anything is wiped, showing why it is refused.
+    /// Returns whether it can be installed.
+    fn check_content_page(&mut self, content: &Path, policy: &ExtractPolicy) -> crossterm::Result<bool> {
+        let check = match content.is_dir() {
+            true => check_folder(content, policy),
+            false => check_archive(content, policy),
+        };
+        match check {
+            Ok(()) => Ok(true),
+            Err(error) => {
+                self.config.log(format!("Error: checking {}: {}", content.display(), error).as_str());
+                let filename = content.file_name().unwrap_or_default().to_string_lossy();
+                self.error_page(&format!("Impossible d'extraire {}: {}", filename, error))?; //lang
+                Ok(false)
+            }
+        }
+    }
+
+    pub fn unzip_page(&mut self, archive: &str, folderpath: &str, policy: &ExtractPolicy) -> crossterm::Result<bool> {
         let height = self.terminal_height / 2u16;
         let mut stdout = io::stdout();
         execute!(stdout,
@@ -271,31 +1150,410 @@ impl Display {
             cursor::MoveTo(0, height - 2))?;
 
         self.write_centered("Installation en cours...")?; //lang
-        unzip_file(filename, folderpath)?;
+        let rejected = match extract_archive(Path::new(archive), Path::new(folderpath), policy) {
+            Ok(rejected) => rejected,
+            Err(error) => {
+                self.config.log(format!("Error: extracting {}: {}", archive, error).as_str());
+                let filename = Path::new(archive).file_name().unwrap_or_default().to_string_lossy();
+                self.error_page(&format!("Impossible d'extraire {}: {}", filename, error))?; //lang
+                return Ok(false);
+            }
+        };
         
         execute!(stdout,
             terminal::Clear(terminal::ClearType::All),
             cursor::MoveTo(0, height - 2))?;
 
         self.write_centered("Installation terminée...")?; //lang
+        self.rejected_page(&rejected)?;
+        Ok(true)
+    }
+
+    /// Copies the folder `from` into `folderpath` following `policy`, then lists the files left out.
+    /// Returns whether the folder was copied, an error page being shown otherwise.
+    fn copy_folder_page(&mut self, from: &Path, folderpath: &str, policy: &ExtractPolicy) -> crossterm::Result<bool> {
+        self.config.log(format!("copying modpack folder: {}", from.display()).as_str());
+        match copy_folder_checked(from, Path::new(folderpath), policy) {
+            Ok(rejected) => {
+                self.rejected_page(&rejected)?;
+                Ok(true)
+            }
+            Err(error) => {
+                self.config.log(format!("Error: copying {}: {}", from.display(), error).as_str());
+                self.error_page(&format!("Impossible de copier {}: {}", from.display(), error))?; //lang
+                Ok(false)
+            }
+        }
+    }
+
+    /// Lists the files the extraction policy left out and waits for a key. Shows nothing when there are none.
+    pub fn rejected_page(&mut self, rejected: &[Rejected]) -> crossterm::Result<()> {
+        if rejected.is_empty() {
+            return Ok(());
+        }
+        rejected.iter().for_each(|entry| self.config.log(format!("rejected: {} ({})", entry.path, entry.reason).as_str()));
+
+        let mut stdout = io::stdout();
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 2))?;
+        self.write_stylized_centered(format!("{} fichier(s) ignoré(s) par sécurité", rejected.len()).as_str().with(Color::Yellow).attribute(Attribute::Bold))?; //lang
+        let lines = self.terminal_height.saturating_sub(9) as usize;
+        rejected.iter().take(lines).enumerate().for_each(|(index, entry)| {
+            execute!(stdout, cursor::MoveTo(0, 4 + index as u16)).unwrap();
+            self.write_centered(&format!("{} : {}", entry.path, entry.reason)).unwrap();
+        });
+        if rejected.len() > lines {
+            execute!(stdout, cursor::MoveTo(0, 4 + lines as u16))?;
+            self.write_stylized_centered(format!("... et {} autres, voir debug.txt", rejected.len() - lines).as_str().with(Color::DarkGrey))?; //lang
+        }
+        execute!(stdout, cursor::MoveTo(0, self.terminal_height.saturating_sub(2)))?;
+        self.write_stylized_centered("Appuyez sur une touche pour continuer".with(Color::DarkGrey).attribute(Attribute::Dim))?; //lang
+
+        loop {
+            if let Event::Key(KeyEvent {kind: KeyEventKind::Press, ..}) = event::read()? {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Runs the modloader installer headlessly, waits for it and shows whether it succeeded.
+    /// The installer output is written to the debug file, and shown on screen if it failed.
+    /// Returns whether the installer succeeded.
+    pub fn executable_page(&mut self, pack: &Pack, filepath: &str, args: &[String]) -> crossterm::Result<bool> {
+        let height = self.terminal_height / 2u16;
+        let mut stdout = io::stdout();
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, height - 2))?;
+
+        self.write_centered(&format!("Lancement de l'installateur {}", pack.modloader_kind.label()))?; //lang
+        let java = select_java(&self.java_installs, pack.java_version).install().map(|install| install.path.clone());
+        self.config.log(format!("installer java: {:?}", &java).as_str());
+        let output = match run_installer(filepath, args, java.as_deref()) {
+            Ok(output) => output,
+            Err(error) => {
+                self.config.log(format!("Error: running {}: {}", filepath, error).as_str());
+                self.error_page(&format!("Impossible de lancer l'installateur: {}", error))?; //lang
+                return Ok(false);
+            }
+        };
+
+        let stdout_text = String::from_utf8_lossy(&output.stdout).to_string();
+        let stderr_text = String::from_utf8_lossy(&output.stderr).to_string();
+        self.config.log(format!("installer exit status: {}", output.status).as_str());
+        self.config.log(format!("installer stdout:\n{}", stdout_text).as_str());
+        self.config.log(format!("installer stderr:\n{}", stderr_text).as_str());
+
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, height - 2))?;
+
+        if output.status.success() {
+            self.write_stylized_centered("Installation du modloader terminée !".with(Color::Green))?; //lang
+            sleep(Duration::from_secs(1));
+            return Ok(true);
+        }
+
+        let status = match output.status.code() {
+            Some(code) => format!("L'installateur a échoué (code {})", code), //lang
+            None => "L'installateur a été interrompu".to_string(), //lang
+        };
+        self.write_stylized_centered(status.as_str().with(Color::Red).attribute(Attribute::Bold))?;
+
+        let failure_output = if stderr_text.trim().is_empty() {stdout_text} else {stderr_text};
+        let lines: Vec<&str> = failure_output.lines().filter(|line| !line.trim().is_empty()).collect();
+        let max_lines = (self.terminal_height.saturating_sub(height + 2) as usize).min(10);
+        lines[lines.len().saturating_sub(max_lines)..].iter().enumerate().for_each(|(index, line)| {
+            execute!(stdout, cursor::MoveTo(0, height + 1 + index as u16)).unwrap();
+            self.write_stylized_centered(line.with(Color::DarkGrey)).unwrap();
+        });
+        Ok(false)
+    }
+
+    /// Installs Fabric or Quilt without their GUI installer: the loader profile is fetched from the meta API
+    /// and written in the `versions` folder. Returns the id of the installed version, `None` if it failed.
+    pub fn modloader_page(&mut self, kind: ModloaderKind, meta_url: &str, minecraft_version: &str, loader_version: &str) -> crossterm::Result<Option<String>> {
+        let height = self.terminal_height / 2u16;
+        let mut stdout = io::stdout();
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, height - 2))?;
+
+        self.write_centered(&format!("Installation de {} {} pour Minecraft {}...", kind.label(), loader_version, minecraft_version))?; //lang
+        let result = fetch_loader_profile(kind, meta_url, minecraft_version, loader_version)
+            .and_then(|profile| install_version_profile(&self.config.minecraft_folder, &profile));
+
+        let version_id = match result {
+            Ok(version_id) => version_id,
+            Err(error) => {
+                self.config.log(format!("Error: {} {} install: {}", kind.label(), loader_version, error).as_str());
+                self.error_page(&format!("Impossible d'installer {} {}: {}", kind.label(), loader_version, error))?; //lang
+                return Ok(None);
+            }
+        };
+
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, height - 2))?;
+
+        self.write_centered("Installation terminée...")?; //lang
+        sleep(Duration::from_secs(1));
+        Ok(Some(version_id))
+    }
+
+    /// Adds or updates the modpack profile in the Minecraft launcher, launching `version_id`.
+    pub fn profile_page(&mut self, pack: &Pack, version_id: &str) -> crossterm::Result<()> {
+        let height = self.terminal_height / 2u16;
+        let mut stdout = io::stdout();
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, height - 2))?;
+
+        let profile = LauncherProfile {
+            id: LauncherProfile::id_from_name(&pack.profile_name),
+            name: pack.profile_name.clone(),
+            icon: pack.profile_icon.clone(),
+            version_id: version_id.to_string(),
+            game_dir: pack.profile_game_dir.clone(),
+            memory: pack.profile_memory.clone(),
+        };
+        self.config.log(format!("launcher profile: {:?}", &profile).as_str());
+
+        self.write_centered(&format!("Création du profil {} dans le launcher...", profile.name))?; //lang
+        if let Err(error) = write_profile(&self.config.minecraft_folder, &self.config.magic_installer_folder, &profile) {
+            self.config.log(format!("Error: {}", error).as_str());
+            self.error_page(&format!("Impossible de modifier le profil du launcher: {}", error))?; //lang
+            return Ok(());
+        }
+
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, height - 2))?;
+
+        self.write_centered(&format!("Profil {} prêt dans le launcher !", profile.name))?; //lang
+        sleep(Duration::from_secs(1));
         Ok(())
     }
 
-    pub fn executable_page(&self, filepath: &str) -> crossterm::Result<()> {
+    /// Shows an error message until a key is pressed.
+    pub fn error_page(&self, message: &str) -> crossterm::Result<()> {
         let height = self.terminal_height / 2u16;
         let mut stdout = io::stdout();
         execute!(stdout,
             terminal::Clear(terminal::ClearType::All),
             cursor::MoveTo(0, height - 2))?;
 
-        self.write_centered("Lancement de l'installateur Fabric")?; //lang
-        launch_executable(filepath);
-        
+        self.write_stylized_centered(format!("Erreur: {}", message).as_str().with(Color::Red).attribute(Attribute::Bold))?; //lang
+        Ok(())
+    }
+
+    /// Downloads `release`, checks it against its hashes and puts it in place of the running installer.
+    /// Returns whether the installer was replaced and must be restarted.
+    pub fn self_update_page(&mut self, release: &Release) -> crossterm::Result<bool> {
+        // the binary gets executed, so an update that can't be checked is refused, whatever require_signatures says
+        if !has_supported_hash(&release.hashes) {
+            self.config.log(format!("Error: update {} has no supported hash", release.version).as_str());
+            self.error_page("la mise à jour n'a pas d'empreinte vérifiable")?; //lang
+            return Ok(false);
+        }
+
+        let path = download_path().unwrap_or_log(&mut self.config.debugfile);
+        let path = path.to_string_lossy();
+        let source = match self.download_page(&path, &release.urls()) {
+            Ok(source) => source,
+            Err(error) => {
+                self.config.log(format!("Couldn't download update: {}", error).as_str());
+                return Ok(false);
+            }
+        };
+        self.config.log(format!("update {} downloaded from {}", release.version, source).as_str());
+        if !self.check_download(&path, &release.hashes)? || !self.verify_signature(&path, &release.signatures())? {
+            return Ok(false);
+        }
+        replace_executable(Path::new(path.as_ref())).unwrap_or_log(&mut self.config.debugfile);
+
+        let height = self.terminal_height / 2u16;
+        execute!(io::stdout(),
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, height - 2))?;
+        self.write_centered(&format!("Mise à jour vers {} terminée, redémarrage...", release.version))?; //lang
+        sleep(Duration::from_secs(1));
+        Ok(true)
+    }
+
+    /// Shows what installing `pack` would do, without changing anything. Up and Down scroll the plan.
+    pub fn preview_page(&mut self, pack: &Pack, from: Option<&Path>) -> crossterm::Result<()> {
+        let height = self.terminal_height / 2u16;
+        execute!(io::stdout(),
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, height - 2))?;
+        self.write_centered("Préparation de la prévisualisation...")?; //lang
+
+        let plan = match plan_install(&self.config, pack, from) {
+            Ok(plan) => plan,
+            Err(error) if is_signature_error(&error) => {
+                self.config.log(format!("Error: preview of {}: {}", &pack.id, error).as_str());
+                return self.signature_page(&error.to_string());
+            }
+            Err(error) => {
+                self.config.log(format!("Error: preview of {}: {}", &pack.id, error).as_str());
+                return self.error_page(&format!("Impossible de prévisualiser l'installation: {}", error)); //lang
+            }
+        };
+        let lines = plan.lines();
+        lines.iter().for_each(|(_, line)| self.config.log(format!("preview: {}", line).as_str()));
+
+        let mut top = 0;
+        loop {
+            let visible = self.terminal_height.saturating_sub(3) as usize;
+            self.draw_plan(&lines, top, visible)?;
+            match event::read()? {
+                Event::Key(KeyEvent {code, kind: KeyEventKind::Press, ..}) => match code {
+                    KeyCode::Up => top = top.saturating_sub(1),
+                    KeyCode::Down if top + visible < lines.len() => top += 1,
+                    KeyCode::Enter | KeyCode::Esc => return Ok(()),
+                    _ => {}
+                },
+                Event::Resize(width, height) => {
+                    self.terminal_width = width;
+                    self.terminal_height = height;
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn draw_plan(&self, lines: &[(PlanLine, String)], top: usize, visible: usize) -> crossterm::Result<()> {
+        let mut stdout = io::stdout();
+        execute!(stdout, terminal::Clear(terminal::ClearType::All))?;
+        lines.iter().skip(top).take(visible).enumerate().for_each(|(index, (kind, line))| {
+            let line = line.as_str();
+            let styled = match kind {
+                PlanLine::Title => line.with(Color::Blue).attribute(Attribute::Bold),
+                PlanLine::Section => line.with(Color::White).attribute(Attribute::Bold),
+                PlanLine::Removed => line.with(Color::Red),
+                PlanLine::Added => line.with(Color::Green),
+                PlanLine::Overwritten => line.with(Color::Yellow),
+                PlanLine::Kept | PlanLine::Info => line.with(Color::DarkGrey),
+                PlanLine::Rejected => line.with(Color::DarkYellow),
+            };
+            execute!(stdout, cursor::MoveTo(0, 1 + index as u16)).unwrap();
+            self.write_stylized_centered(styled).unwrap();
+        });
+        execute!(stdout, cursor::MoveTo(0, self.terminal_height.saturating_sub(1)))?;
+        self.write_stylized_centered("↑ ↓ pour défiler, Entrée ou Esc pour revenir".with(Color::DarkGrey).attribute(Attribute::Dim))?; //lang
+        Ok(())
+    }
+
+    /// Tells the player a file was refused because its signature couldn't be verified, nothing was installed.
+    pub fn signature_page(&self, message: &str) -> crossterm::Result<()> {
+        let height = self.terminal_height / 2u16;
+        let mut stdout = io::stdout();
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, height - 2))?;
+
+        self.write_stylized_centered("Installation refusée : le fichier n'a pas pu être authentifié".with(Color::Red).attribute(Attribute::Bold))?; //lang
+        execute!(stdout, cursor::MoveTo(0, height))?;
+        self.write_stylized_centered(message.with(Color::Red))?;
+        execute!(stdout, cursor::MoveTo(0, height + 2))?;
+        self.write_stylized_centered("Il a peut-être été modifié par un tiers, prévenez l'auteur du modpack".with(Color::DarkGrey))?; //lang
+        Ok(())
+    }
+
+    /// Asks the player to confirm a destructive action, listing what it deletes in `removed` with its size.
+    /// Non is selected first so a stray Enter cancels. Returns true right away with `--yes`.
+    pub fn confirm_page(&mut self, question: &str, removed: &[(String, u64)]) -> crossterm::Result<bool> {
+        let total: u64 = removed.iter().map(|(_, size)| size).sum();
+        self.config.log(format!("confirm: {} ({} items, {} bytes)", question, removed.len(), total).as_str());
+        if self.assume_yes {
+            return Ok(true);
+        }
+
+        let mut yes = false;
+        loop {
+            self.draw_confirm(question, removed, total, yes)?;
+            match event::read()? {
+                Event::Key(KeyEvent {code, kind: KeyEventKind::Press, ..}) => match code {
+                    KeyCode::Left | KeyCode::Right | KeyCode::Up | KeyCode::Down | KeyCode::Tab => yes = !yes,
+                    KeyCode::Char('o') | KeyCode::Char('O') => yes = true,
+                    KeyCode::Char('n') | KeyCode::Char('N') => yes = false,
+                    KeyCode::Enter => break,
+                    KeyCode::Esc => {
+                        yes = false;
+                        break;
+                    }
+                    _ => {}
+                },
+                Event::Resize(width, height) => {
+                    self.terminal_width = width;
+                    self.terminal_height = height;
+                }
+                _ => {}
+            }
+        }
+        self.config.log(format!("confirmed: {}", yes).as_str());
+        if !yes {
+            let height = self.terminal_height / 2u16;
+            execute!(io::stdout(),
+                terminal::Clear(terminal::ClearType::All),
+                cursor::MoveTo(0, height - 2))?;
+            self.write_centered("Action annulée, rien n'a été supprimé")?; //lang
+        }
+        Ok(yes)
+    }
+
+    fn draw_confirm(&self, question: &str, removed: &[(String, u64)], total: u64, yes: bool) -> crossterm::Result<()> {
+        let mut stdout = io::stdout();
+        execute!(stdout,
+            terminal::Clear(terminal::ClearType::All),
+            cursor::MoveTo(0, 2))?;
+        self.write_stylized_centered(question.with(Color::Red).attribute(Attribute::Bold))?;
+
+        execute!(stdout, cursor::MoveTo(0, 4))?;
+        if removed.is_empty() {
+            self.write_centered("Aucun fichier à supprimer")?; //lang
+        } else {
+            self.write_centered(&format!("{} élément(s) supprimé(s), {} au total :", removed.len(), format_size(total)))?; //lang
+        }
+        let lines = self.terminal_height.saturating_sub(11) as usize;
+        removed.iter().take(lines).enumerate().for_each(|(index, (path, size))| {
+            execute!(stdout, cursor::MoveTo(0, 6 + index as u16)).unwrap();
+            self.write_stylized_centered(format!("{} ({})", path, format_size(*size)).as_str().with(Color::Red)).unwrap();
+        });
+        if removed.len() > lines {
+            execute!(stdout, cursor::MoveTo(0, 6 + lines as u16))?;
+            self.write_stylized_centered(format!("... et {} autres", removed.len() - lines).as_str().with(Color::DarkGrey))?; //lang
+        }
+
+        let (yes_label, no_label) = ("Oui", "Non"); //lang
+        let option = |label: &str, selected: bool| match selected {
+            true => format!("> {} <", label).with(Color::Green).attribute(Attribute::Bold),
+            false => format!("  {}  ", label).stylize(),
+        };
+        let options = format!("{}      {}", option(yes_label, yes), option(no_label, !yes));
+        let width = yes_label.len() + no_label.len() + 14;
+        execute!(stdout,
+            cursor::MoveTo(0, self.terminal_height.saturating_sub(4)),
+            Print(" ".repeat(self.terminal_width.saturating_sub(width as u16) as usize / 2)),
+            Print(options))?;
+        execute!(stdout, cursor::MoveTo(0, self.terminal_height.saturating_sub(2)))?;
+        self.write_stylized_centered("← → pour choisir, Entrée pour valider, Esc pour annuler".with(Color::DarkGrey).attribute(Attribute::Dim))?; //lang
+        Ok(())
+    }
+
+    /// Empties the download cache and shows the space freed.
+    pub fn clear_cache_page(&mut self) -> crossterm::Result<()> {
+        let height = self.terminal_height / 2u16;
+        let mut stdout = io::stdout();
         execute!(stdout,
             terminal::Clear(terminal::ClearType::All),
             cursor::MoveTo(0, height - 2))?;
 
-        self.write_centered("Lancement terminé...")?; //lang
+        let freed = self.cache.lock().unwrap().clear().unwrap_or_log(&mut self.config.debugfile);
+        self.config.log(format!("cache cleared: {} bytes", freed).as_str());
+        self.write_centered(&format!("Cache vidé : {} libérés", format_size(freed)))?; //lang
         sleep(Duration::from_secs(1));
         Ok(())
     }
@@ -0,0 +1,218 @@
+use std::collections::HashMap;
+use std::fs::{self, File};
+use std::io::{Write, self};
+use std::path::{Path, PathBuf};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::{Deserialize, Serialize};
+
+use crate::files::{file_hash, verify_hashes};
+
+pub const CACHE_FOLDER: &str = "cache";
+const INDEX_FILE: &str = "index.json";
+/// Default size limit of the cache, in Mo.
+pub const DEFAULT_CACHE_SIZE: u64 = 2048;
+
+/// Downloaded files kept in `magic_installer\cache\`, named by their SHA-256, so reinstalling or switching packs
+/// doesn't download identical files again. The least recently used files are evicted past `max_size`.
+#[derive(Debug)]
+pub struct Cache {
+    folder: PathBuf,
+    max_size: u64,
+    entries: Vec<CacheEntry>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheEntry {
+    sha256: String,
+    size: u64,
+    /// Seconds since the epoch of the last time the file was stored or used.
+    last_used: u64,
+    /// Other hashes known for the file, as `algorithm:hex`, so files listed with SHA-1 or SHA-512 are found too.
+    #[serde(default)]
+    aliases: Vec<String>,
+}
+
+impl Cache {
+    /// Opens the cache of the installer, `max_size` being in bytes.
+    pub fn open(magic_installer_folder: &str, max_size: u64) -> Cache {
+        let folder = Path::new(magic_installer_folder).join(CACHE_FOLDER);
+        let entries = fs::read_to_string(folder.join(INDEX_FILE)).ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Cache { folder, max_size, entries }
+    }
+
+    /// Total size of the cached files, in bytes.
+    pub fn size(&self) -> u64 {
+        self.entries.iter().map(|entry| entry.size).sum()
+    }
+
+    /// Copies the cached file matching `hashes` to `target`, returns whether it was found.
+    /// A cached file that no longer matches its hashes is dropped.
+    pub fn fetch(&mut self, hashes: &HashMap<String, String>, target: &Path) -> io::Result<bool> {
+        let index = match self.find(hashes) {
+            Some(index) => index,
+            None => return Ok(false),
+        };
+        let path = self.folder.join(&self.entries[index].sha256);
+        if fs::copy(&path, target).is_err() || verify_hashes(target, hashes).is_err() {
+            let _ = fs::remove_file(&path);
+            self.entries.remove(index);
+            self.save()?;
+            return Ok(false);
+        }
+        self.entries[index].last_used = now();
+        self.save()?;
+        Ok(true)
+    }
+
+    /// Adds a downloaded file to the cache, `hashes` being the ones it was checked against.
+    pub fn store(&mut self, path: &Path, hashes: &HashMap<String, String>) -> io::Result<()> {
+        let sha256 = file_hash(path, "sha256")?.unwrap_or_default();
+        let aliases: Vec<String> = hashes.iter()
+            .filter(|(algorithm, _)| algorithm.as_str() != "sha256")
+            .map(|(algorithm, hash)| format!("{}:{}", algorithm, hash.to_lowercase()))
+            .collect();
+        match self.entries.iter_mut().find(|entry| entry.sha256 == sha256) {
+            Some(entry) => {
+                entry.last_used = now();
+                aliases.into_iter().for_each(|alias| if !entry.aliases.contains(&alias) {entry.aliases.push(alias)});
+            }
+            None => {
+                fs::create_dir_all(&self.folder)?;
+                let size = fs::copy(path, self.folder.join(&sha256))?;
+                self.entries.push(CacheEntry { sha256, size, last_used: now(), aliases });
+            }
+        }
+        self.evict()?;
+        self.save()
+    }
+
+    /// Deletes every cached file, returns the space freed in bytes.
+    pub fn clear(&mut self) -> io::Result<u64> {
+        let size = self.size();
+        if self.folder.exists() {
+            fs::remove_dir_all(&self.folder)?;
+        }
+        self.entries.clear();
+        Ok(size)
+    }
+
+    fn find(&self, hashes: &HashMap<String, String>) -> Option<usize> {
+        hashes.iter().find_map(|(algorithm, hash)| {
+            let hash = hash.to_lowercase();
+            let alias = format!("{}:{}", algorithm, hash);
+            self.entries.iter().position(|entry| match algorithm.as_str() {
+                "sha256" => entry.sha256 == hash,
+                _ => entry.aliases.contains(&alias),
+            })
+        })
+    }
+
+    /// Removes the least recently used files until the cache fits in its size limit.
+    fn evict(&mut self) -> io::Result<()> {
+        self.entries.sort_by_key(|entry| entry.last_used);
+        while self.size() > self.max_size && !self.entries.is_empty() {
+            let entry = self.entries.remove(0);
+            match fs::remove_file(self.folder.join(&entry.sha256)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    fn save(&self) -> io::Result<()> {
+        fs::create_dir_all(&self.folder)?;
+        let mut file = File::create(self.folder.join(INDEX_FILE))?;
+        file.write_all(serde_json::to_string_pretty(&self.entries)?.as_bytes())?;
+        Ok(())
+    }
+}
+
+fn now() -> u64 {
+    SystemTime::now().duration_since(UNIX_EPOCH).map_or(0, |duration| duration.as_secs())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+    use crate::files::bytes_hash;
+
+    fn test_folder(name: &str) -> PathBuf {
+        let folder = env::temp_dir().join(format!("magic_installer_cache_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    /// Writes `content` in `folder` and returns its path with its hashes.
+    fn downloaded(folder: &Path, name: &str, content: &[u8]) -> (PathBuf, HashMap<String, String>) {
+        let path = folder.join(name);
+        fs::write(&path, content).unwrap();
+        let hashes = ["sha1", "sha256"].iter()
+            .map(|algorithm| (algorithm.to_string(), bytes_hash(content, algorithm).unwrap()))
+            .collect();
+        (path, hashes)
+    }
+
+    fn only(hashes: &HashMap<String, String>, algorithm: &str) -> HashMap<String, String> {
+        HashMap::from([(algorithm.to_string(), hashes[algorithm].clone())])
+    }
+
+    #[test]
+    fn least_recently_used_files_are_evicted() {
+        let folder = test_folder("evict");
+        let mut cache = Cache::open(&folder.to_string_lossy(), 250);
+        let files: Vec<_> = ["a.jar", "b.jar", "c.jar"].iter().enumerate()
+            .map(|(index, name)| downloaded(&folder, name, &[index as u8; 100]))
+            .collect();
+        // files stored in the same second are told apart by making the first ones older
+        for (age, (path, hashes)) in files.iter().enumerate() {
+            cache.store(path, hashes).unwrap();
+            cache.entries.iter_mut().find(|entry| entry.sha256 == hashes["sha256"]).unwrap().last_used = age as u64;
+        }
+        assert_eq!(cache.size(), 200);
+
+        let target = folder.join("target.jar");
+        assert!(!cache.fetch(&files[0].1, &target).unwrap());
+        assert!(cache.fetch(&files[1].1, &target).unwrap());
+        assert!(cache.fetch(&files[2].1, &target).unwrap());
+        assert!(!folder.join(CACHE_FOLDER).join(&files[0].1["sha256"]).exists());
+        // the index is saved with the eviction
+        assert_eq!(Cache::open(&folder.to_string_lossy(), 250).size(), 200);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn files_are_found_by_any_of_their_hashes() {
+        let folder = test_folder("alias");
+        let mut cache = Cache::open(&folder.to_string_lossy(), 1000);
+        let (path, hashes) = downloaded(&folder, "a.jar", b"mod");
+        cache.store(&path, &hashes).unwrap();
+
+        let target = folder.join("target.jar");
+        assert!(cache.fetch(&only(&hashes, "sha1"), &target).unwrap());
+        assert_eq!(fs::read(&target).unwrap(), b"mod");
+        assert!(cache.fetch(&only(&hashes, "sha256"), &target).unwrap());
+        assert!(!cache.fetch(&HashMap::from([("sha1".to_string(), "00".to_string())]), &target).unwrap());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn corrupted_files_are_dropped() {
+        let folder = test_folder("corrupted");
+        let mut cache = Cache::open(&folder.to_string_lossy(), 1000);
+        let (path, hashes) = downloaded(&folder, "a.jar", b"mod");
+        cache.store(&path, &hashes).unwrap();
+        fs::write(folder.join(CACHE_FOLDER).join(&hashes["sha256"]), b"not the mod").unwrap();
+
+        assert!(!cache.fetch(&hashes, &folder.join("target.jar")).unwrap());
+        assert_eq!(cache.size(), 0);
+        assert!(!folder.join(CACHE_FOLDER).join(&hashes["sha256"]).exists());
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
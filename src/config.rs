@@ -1,52 +1,211 @@
 use std::collections::HashMap;
 use std::fs::File;
+use std::path::Path;
 
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+
+use crate::cache::DEFAULT_CACHE_SIZE;
+use crate::curseforge::CURSEFORGE_API_URL;
+use crate::extract::ExtractPolicy;
 use crate::files::create_folder;
+use crate::java::JavaRequirement;
+use crate::http::HttpSettings;
+use crate::rate_limit::parse_rate;
+use crate::scheduler::DEFAULT_CONCURRENT_DOWNLOADS;
+use crate::modloader::ModloaderKind;
+use crate::record::InstallRecord;
+use crate::signature::signature_sources;
 
 use std::io::Write;
 
-pub const VERSION: &str = "v2.1.1";
+pub const VERSION: &str = concat!("v", env!("CARGO_PKG_VERSION"));
 pub const MAIN_TITLE: &str = include_str!("../title.txt");
 pub const AUTHOR: &str = "RICHELET Arthur - 2023";
 pub const CONTROLS: &str = "↑ ↓ pour naviguer, Entrée pour valider, Esc pour quitter";
 pub const BOTTOM_TEXT: &str = "Un installateur pour les gouverner tous";
 
 pub const MINECRAFT_FOLDER: &str = "%appdata%\\.minecraft\\";
-pub const MAIN_MENU_OPTIONS: &[&str] = &["Installer le modpack", "Installer fabric", "Supprimer les fichiers du modpack", "Quitter (esc)"];
 pub const FILES_TO_REMOVE: &[&str] = &["mods", "config"];
+pub const DEFAULT_PACK_NAME: &str = "Modpack";
+pub const DEFAULT_PROFILE_ICON: &str = "Furnace";
+pub const INSTANCES_FOLDER: &str = "instances\\";
+pub const PACK_PREFIX: &str = "pack.";
+/// Folder of the installer where imported modpacks are extracted before installing.
+pub const IMPORTS_FOLDER: &str = "imports\\";
 
 // ---- Config ---- //
 
 #[derive(Debug)]
 pub struct Config {
-    pub modpack_url: String,
-    pub modloader_url: String,
-    pub modloader_execname: String,
+    pub packs: Vec<Pack>,
     pub minecraft_folder: String,
     pub magic_installer_folder: String,
+    /// Files of a manifest downloaded at the same time.
+    pub download_concurrency: usize,
+    /// Size limit of the download cache, in bytes.
+    pub cache_max_size: u64,
+    pub http: HttpSettings,
+    /// Limit of the downloads in bytes per second, 0 for no limit.
+    pub max_download_rate: u64,
+    /// Release endpoint checked for a newer installer, see `update::Release`.
+    pub update_url: Option<String>,
+    /// Whether modpacks and modloader installers must be signed, see `signature`. On unless `require_signatures=false`,
+    /// installer updates are always checked.
+    pub require_signatures: bool,
     pub debugfile: File,
     pub debug: bool,
 }
 
+/// How the modpack at `modpack_url` is described.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PackFormat {
+    /// A zip extracted in the instance folder.
+    Zip,
+    /// A JSON manifest listing each file, see `manifest::Manifest`.
+    Manifest,
+    /// A Modrinth `.mrpack`, see `mrpack::read_mrpack`.
+    Mrpack,
+    /// A CurseForge export zip, see `curseforge::read_curseforge`.
+    CurseForge,
+    /// The `pack.toml` of a packwiz modpack, see `packwiz::read_packwiz`.
+    Packwiz,
+}
+
+impl PackFormat {
+    pub fn from(format: &str) -> Option<PackFormat> {
+        match format.to_lowercase().as_str() {
+            "zip" => Some(PackFormat::Zip),
+            "manifest" | "json" => Some(PackFormat::Manifest),
+            "mrpack" | "modrinth" => Some(PackFormat::Mrpack),
+            "curseforge" => Some(PackFormat::CurseForge),
+            "packwiz" => Some(PackFormat::Packwiz),
+            _ => None,
+        }
+    }
+
+    /// Guesses the format from the extension of the url, ignoring its query string.
+    pub fn from_url(url: &str) -> PackFormat {
+        let path = url.split(['?', '#']).next().unwrap_or_default().to_lowercase();
+        if path.ends_with(".json") {
+            PackFormat::Manifest
+        } else if path.ends_with(".mrpack") {
+            PackFormat::Mrpack
+        } else if path.ends_with(".toml") {
+            PackFormat::Packwiz
+        } else {
+            PackFormat::Zip
+        }
+    }
+}
+
+/// A modpack declared in the config, with its modloader, launcher profile and instance folder.
+#[derive(Debug, Clone)]
+pub struct Pack {
+    pub id: String,
+    pub name: String,
+    pub modpack_url: String,
+    /// Urls serving the same modpack as `modpack_url`, tried in order when it fails.
+    pub modpack_mirrors: Vec<String>,
+    /// Expected hex digests of the downloaded modpack, keyed by algorithm.
+    pub modpack_hashes: HashMap<String, String>,
+    /// Urls of the signature of the modpack, for links `.sig` can't be added to like share links.
+    /// Empty for the `.sig` next to each url, see `signature::signature_sources`.
+    pub modpack_signature_urls: Vec<String>,
+    pub format: PackFormat,
+    pub modloader_url: Option<String>,
+    pub modloader_mirrors: Vec<String>,
+    pub modloader_hashes: HashMap<String, String>,
+    /// Same as `modpack_signature_urls` for the modloader installer.
+    pub modloader_signature_urls: Vec<String>,
+    pub modloader_execname: Option<String>,
+    pub modloader_kind: ModloaderKind,
+    pub minecraft_version: Option<String>,
+    pub modloader_version: Option<String>,
+    pub modloader_version_id: Option<String>,
+    pub modloader_meta_url: Option<String>,
+    pub modloader_args: Vec<String>,
+    pub java_version: Option<JavaRequirement>,
+    pub curseforge_api_url: String,
+    pub curseforge_api_key: Option<String>,
+    pub profile_name: String,
+    pub profile_icon: String,
+    pub profile_game_dir: Option<String>,
+    pub profile_memory: Option<String>,
+    pub instance_folder: String,
+    /// What the modpack archives may extract, see `extract::ExtractPolicy`.
+    pub extract_policy: ExtractPolicy,
+}
+
 impl Config {
     pub fn from(config: &str, debug: bool) -> Config {
+        let pack_ids = Config::pack_ids(config);
         let config = Config::parse_hashmap(config, "\n", "=");
 
         let magic_installer_folderpath = format!("{}{}", get_env_path(MINECRAFT_FOLDER), "magic_installer\\");
         create_folder(magic_installer_folderpath.as_str());
 
+        let minecraft_folder = get_env_path(MINECRAFT_FOLDER);
+
+        // without any `pack.<id>.` entry, the top level keys describe the only modpack,
+        // otherwise they are the defaults every declared pack inherits.
+        let packs: Vec<Pack> = if pack_ids.is_empty() {
+            vec![Pack::from(None, &config, &minecraft_folder)]
+        } else {
+            pack_ids.iter().map(|id| {
+                let prefix = format!("{}{}.", PACK_PREFIX, id);
+                let mut pack_config: HashMap<String, String> = config.iter()
+                    .filter(|(key, _)| !key.starts_with(PACK_PREFIX))
+                    .map(|(key, value)| (key.clone(), value.clone()))
+                    .collect();
+                config.iter().for_each(|(key, value)| {
+                    if let Some(key) = key.strip_prefix(&prefix) {
+                        pack_config.insert(key.to_string(), value.clone());
+                    }
+                });
+                Pack::from(Some(id), &pack_config, &minecraft_folder)
+            }).collect()
+        };
+
         Config {
-            modpack_url: config.get("modpack_url").unwrap().to_string(),
-            modloader_url: config.get("modloader_url").unwrap().to_string(),
-            modloader_execname: config.get("modloader_execname").unwrap().to_string(),
-            minecraft_folder: get_env_path(MINECRAFT_FOLDER),
+            packs,
+            minecraft_folder,
             magic_installer_folder: magic_installer_folderpath,
+            download_concurrency: config.get("download.concurrency")
+                .map(|concurrency| concurrency.parse().expect("download.concurrency must be a number"))
+                .unwrap_or(DEFAULT_CONCURRENT_DOWNLOADS),
+            cache_max_size: config.get("cache.max_size")
+                .map(|size| size.parse().expect("cache.max_size must be a number of Mo"))
+                .unwrap_or(DEFAULT_CACHE_SIZE) * 1024 * 1024,
+            http: HttpSettings::from(&config),
+            max_download_rate: config.get("max_download_rate")
+                .map(|rate| parse_rate(rate).unwrap_or_else(|| panic!("Invalid max_download_rate '{}'", rate)))
+                .unwrap_or(0),
+            update_url: config.get("update_url").map(|url| direct_download_url(url)),
+            require_signatures: config.get("require_signatures").is_none_or(|value| value != "false"),
             debugfile: File::create(format!("{}{}", get_env_path(MINECRAFT_FOLDER), "magic_installer\\debug.txt")).unwrap(),
-            debug: debug,
+            debug,
         }
 
     }
 
+    /// Ids of the `pack.<id>.` entries, in the order they first appear in the config.
+    fn pack_ids(config: &str) -> Vec<String> {
+        let mut ids: Vec<String> = Vec::new();
+        config.lines().for_each(|line| {
+            let id = line.split_once('=')
+                .and_then(|(key, _)| key.trim().strip_prefix(PACK_PREFIX))
+                .and_then(|key| key.split_once('.'))
+                .map(|(id, _)| id.to_string());
+            if let Some(id) = id {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+            }
+        });
+        ids
+    }
+
     fn parse_hashmap(target: &str, entries_separator: &str, key_value_separator: &str) -> HashMap<String, String> {
         let mut result: HashMap<String, String> = HashMap::new();
         let entries = target.split(entries_separator);
@@ -67,6 +226,128 @@ impl Config {
     }
 }
 
+impl Pack {
+    /// Builds a pack from its config entries, `id` defaults to the slug of its name.
+    pub fn from(id: Option<&str>, config: &HashMap<String, String>, minecraft_folder: &str) -> Pack {
+        let name = config.get("name").or(config.get("pack_name")).cloned().unwrap_or(DEFAULT_PACK_NAME.to_string());
+        let id = id.map(str::to_string).unwrap_or(slugify(&name));
+
+        // the modpack gets its own game directory, unless `instance_folder=.` asks for the shared .minecraft
+        let instance_folder = match config.get("instance_folder").map(String::as_str) {
+            Some(".") => minecraft_folder.to_string(),
+            Some(folder) => {
+                let folder = get_env_path(folder);
+                let folder = if folder.contains(':') || folder.starts_with('\\') {folder} else {format!("{}{}", minecraft_folder, folder)};
+                if folder.ends_with('\\') {folder} else {folder + "\\"}
+            }
+            None => format!("{}{}{}\\", minecraft_folder, INSTANCES_FOLDER, slugify(&id)),
+        };
+        let profile_game_dir = match config.get("profile.game_dir") {
+            Some(game_dir) => Some(get_env_path(game_dir)),
+            None if instance_folder != minecraft_folder => Some(instance_folder.trim_end_matches('\\').to_string()),
+            None => None,
+        };
+
+        let minecraft_version = config.get("minecraft_version").cloned();
+        let modloader_version = config.get("modloader.version").cloned();
+        let modloader_kind = match config.get("modloader.kind") {
+            Some(kind) => ModloaderKind::from(kind).unwrap_or_else(|| panic!("Unknown modloader.kind '{}'", kind)),
+            None => ModloaderKind::Fabric,
+        };
+        let modloader_args = config.get("modloader.args").map(|args| {
+            args.split_whitespace().map(|arg| arg
+                .replace("{minecraft_version}", minecraft_version.as_deref().unwrap_or_default())
+                .replace("{loader_version}", modloader_version.as_deref().unwrap_or_default())
+                .replace("{minecraft_folder}", minecraft_folder)
+                .replace("{instance_folder}", &instance_folder)
+            ).collect()
+        }).unwrap_or_default();
+        let java_version = config.get("java_version").map(|version| {
+            let min: u32 = version.parse().expect("java_version must be a number");
+            let max: u32 = config.get("java_max_version").map_or(min, |max| max.parse().expect("java_max_version must be a number"));
+            JavaRequirement { min, max: max.max(min) }
+        });
+
+        // urls are separated by commas, the first one is used unless it fails
+        let mut modpack_urls = parse_urls(config.get("modpack_url").unwrap_or_else(|| panic!("No modpack_url for pack '{}'", id)));
+        if modpack_urls.is_empty() {
+            panic!("No modpack_url for pack '{}'", id);
+        }
+        let modpack_url = modpack_urls.remove(0);
+        let mut modloader_urls = config.get("modloader_url").map(|urls| parse_urls(urls)).unwrap_or_default();
+        let modloader_url = if modloader_urls.is_empty() {None} else {Some(modloader_urls.remove(0))};
+        let format = match config.get("format") {
+            Some(format) => PackFormat::from(format).unwrap_or_else(|| panic!("Unknown format '{}'", format)),
+            None => PackFormat::from_url(&modpack_url),
+        };
+
+        Pack {
+            modpack_url,
+            modpack_mirrors: modpack_urls,
+            modpack_hashes: config.get("modpack_hash").map(|hashes| parse_hashes(hashes)).unwrap_or_default(),
+            modpack_signature_urls: config.get("modpack_signature_url").map(|urls| parse_urls(urls)).unwrap_or_default(),
+            format,
+            modloader_url,
+            modloader_mirrors: modloader_urls,
+            modloader_hashes: config.get("modloader_hash").map(|hashes| parse_hashes(hashes)).unwrap_or_default(),
+            modloader_signature_urls: config.get("modloader_signature_url").map(|urls| parse_urls(urls)).unwrap_or_default(),
+            modloader_execname: config.get("modloader_execname").cloned(),
+            modloader_kind,
+            minecraft_version,
+            modloader_version,
+            modloader_version_id: config.get("modloader.version_id").cloned(),
+            modloader_meta_url: config.get("modloader.meta_url").cloned(),
+            modloader_args,
+            java_version,
+            curseforge_api_url: config.get("curseforge.api_url").cloned().unwrap_or(CURSEFORGE_API_URL.to_string()),
+            curseforge_api_key: config.get("curseforge.api_key").cloned(),
+            profile_name: config.get("profile.name").cloned().unwrap_or(name.clone()),
+            profile_icon: config.get("profile.icon").cloned().unwrap_or(DEFAULT_PROFILE_ICON.to_string()),
+            profile_game_dir,
+            profile_memory: config.get("profile.memory").cloned(),
+            instance_folder,
+            extract_policy: ExtractPolicy::from(config),
+            name,
+            id,
+        }
+    }
+
+    /// Whether the modpack has an install record, or looks installed in its instance folder.
+    pub fn is_installed(&self, magic_installer_folder: &str) -> bool {
+        InstallRecord::path(magic_installer_folder, &self.id).exists() || Path::new(&self.instance_folder).join("mods").exists()
+    }
+
+    /// `modpack_url` followed by its mirrors.
+    pub fn modpack_urls(&self) -> Vec<String> {
+        let mut urls = vec![self.modpack_url.clone()];
+        urls.extend(self.modpack_mirrors.iter().cloned());
+        urls
+    }
+
+    /// Where the signature of the modpack is: `modpack_signature_url`, else next to each of `modpack_urls`.
+    pub fn modpack_signatures(&self) -> Vec<String> {
+        signature_sources(&self.modpack_signature_urls, &self.modpack_urls())
+    }
+
+    /// Where the signature of the modloader installer downloaded from `urls` is.
+    pub fn modloader_signatures(&self, urls: &[String]) -> Vec<String> {
+        signature_sources(&self.modloader_signature_urls, urls)
+    }
+
+    /// Uses the Minecraft and modloader versions an imported modpack recorded at install.
+    pub fn apply_record(&mut self, record: &InstallRecord) {
+        if let Some(kind) = record.modloader {
+            self.modloader_kind = kind;
+        }
+        if record.minecraft_version.is_some() {
+            self.minecraft_version = record.minecraft_version.clone();
+        }
+        if record.modloader_version.is_some() {
+            self.modloader_version = record.modloader_version.clone();
+        }
+    }
+}
+
 pub fn get_env_path(path: &str) -> String {
     if path.starts_with('%') {
         let path_splitted: Vec<&str> = path.split('%').collect();
@@ -78,4 +359,83 @@ pub fn get_env_path(path: &str) -> String {
         return path + path_splitted[2];
     }
     path.to_string()
-}
\ No newline at end of file
+}
+
+/// Turns a display name into a lowercase name usable in folder names and ids, e.g. `Mon Pack!` -> `mon-pack`.
+pub fn slugify(name: &str) -> String {
+    let slug: String = name.to_lowercase().chars()
+        .map(|c| if c.is_ascii_alphanumeric() {c} else {'-'})
+        .collect();
+    slug.trim_matches('-').to_string()
+}
+
+/// Splits a comma separated list of urls, each made a direct download link.
+fn parse_urls(urls: &str) -> Vec<String> {
+    urls.split(',').map(str::trim).filter(|url| !url.is_empty()).map(direct_download_url).collect()
+}
+
+/// Parses `algorithm:hex` digests separated by commas, e.g. `sha256:9f86d08...`.
+fn parse_hashes(hashes: &str) -> HashMap<String, String> {
+    hashes.split(',')
+        .filter_map(|hash| hash.trim().split_once(':'))
+        .map(|(algorithm, hash)| (algorithm.trim().to_lowercase(), hash.trim().to_string()))
+        .collect()
+}
+
+/// Turns the share links of Dropbox, Google Drive and OneDrive into direct download links, other urls are kept.
+pub fn direct_download_url(url: &str) -> String {
+    let (address, query) = url.split_once('?').unwrap_or((url, ""));
+    let host = address.split('/').nth(2).unwrap_or_default().to_lowercase();
+
+    if host.ends_with("dropbox.com") {
+        // dl=0 shows the preview page, dl=1 sends the file
+        let mut params: Vec<&str> = query.split('&').filter(|param| !param.is_empty() && !param.starts_with("dl=")).collect();
+        params.push("dl=1");
+        return format!("{}?{}", address, params.join("&"));
+    }
+
+    if host == "drive.google.com" {
+        // https://drive.google.com/file/d/<id>/view or https://drive.google.com/open?id=<id>
+        let id = address.split("/d/").nth(1).and_then(|path| path.split('/').next())
+            .or(query.split('&').find_map(|param| param.strip_prefix("id=")));
+        if let Some(id) = id {
+            return format!("https://drive.google.com/uc?export=download&confirm=t&id={}", id);
+        }
+    }
+
+    if host == "1drv.ms" || host == "onedrive.live.com" {
+        // the shares api takes the share link encoded as `u!<base64url>`
+        return format!("https://api.onedrive.com/v1.0/shares/u!{}/root/content", URL_SAFE_NO_PAD.encode(url));
+    }
+
+    url.to_string()
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn dropbox_links_download_directly() {
+        assert_eq!(direct_download_url("https://www.dropbox.com/s/abc/pack.zip?dl=0"), "https://www.dropbox.com/s/abc/pack.zip?dl=1");
+        assert_eq!(direct_download_url("https://www.dropbox.com/scl/fi/abc/pack.zip?rlkey=xyz&dl=0"), "https://www.dropbox.com/scl/fi/abc/pack.zip?rlkey=xyz&dl=1");
+        assert_eq!(direct_download_url("https://dropbox.com/s/abc/pack.zip"), "https://dropbox.com/s/abc/pack.zip?dl=1");
+    }
+
+    #[test]
+    fn google_drive_links_download_directly() {
+        let expected = "https://drive.google.com/uc?export=download&confirm=t&id=FILE_ID";
+        assert_eq!(direct_download_url("https://drive.google.com/file/d/FILE_ID/view?usp=sharing"), expected);
+        assert_eq!(direct_download_url("https://drive.google.com/open?id=FILE_ID"), expected);
+    }
+
+    #[test]
+    fn onedrive_links_use_the_shares_api() {
+        let url = "https://1drv.ms/u/s!abc";
+        assert_eq!(direct_download_url(url), format!("https://api.onedrive.com/v1.0/shares/u!{}/root/content", URL_SAFE_NO_PAD.encode(url)));
+    }
+
+    #[test]
+    fn other_links_are_kept() {
+        assert_eq!(direct_download_url("https://example.com/pack.zip?token=1"), "https://example.com/pack.zip?token=1");
+    }
+}
@@ -0,0 +1,148 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::http;
+use crate::manifest::{Category, Manifest, ManifestFile};
+use crate::modloader::ModloaderKind;
+
+pub const CURSEFORGE_API_URL: &str = "https://api.curseforge.com";
+pub const CURSEFORGE_FILE: &str = "curseforge.zip";
+pub const MANIFEST_FILE: &str = "manifest.json";
+/// Hash algorithm ids of the CurseForge API.
+const SHA1_ALGO: u32 = 1;
+
+/// `manifest.json`, at the root of a CurseForge modpack export.
+#[derive(Debug, Deserialize)]
+struct CurseManifest {
+    minecraft: Minecraft,
+    files: Vec<CurseFile>,
+    #[serde(default = "default_overrides")]
+    overrides: String,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Minecraft {
+    version: String,
+    #[serde(default)]
+    mod_loaders: Vec<ModLoader>,
+}
+
+/// A modloader as `forge-47.1.0`, the `primary` one is installed.
+#[derive(Debug, Deserialize)]
+struct ModLoader {
+    id: String,
+    #[serde(default)]
+    primary: bool,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CurseFile {
+    #[serde(rename = "projectID")]
+    project_id: u32,
+    #[serde(rename = "fileID")]
+    file_id: u32,
+    #[serde(default = "default_required")]
+    required: bool,
+}
+
+/// Answer of the API, the requested data is in `data`.
+#[derive(Debug, Deserialize)]
+struct ApiResponse<T> {
+    data: T,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ApiFile {
+    id: u32,
+    file_name: String,
+    /// `None` when the author doesn't allow third party downloads.
+    download_url: Option<String>,
+    #[serde(default)]
+    hashes: Vec<ApiHash>,
+    file_length: Option<u64>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ApiHash {
+    value: String,
+    algo: u32,
+}
+
+fn default_overrides() -> String {
+    "overrides".to_string()
+}
+
+fn default_required() -> bool {
+    true
+}
+
+/// Reads the manifest of a CurseForge export extracted in `folder` and resolves its files with the API at `api_url`.
+/// The files are installed in `mods`, the ones not required are offered in the optional files checklist.
+pub fn read_curseforge(folder: &Path, api_url: &str, api_key: Option<&str>) -> io::Result<Manifest> {
+    parse_curseforge(&fs::read_to_string(folder.join(MANIFEST_FILE))?, folder, api_url, api_key)
+}
+
+/// Reads the content of a CurseForge `manifest.json`, the overrides being in `folder`.
+pub fn parse_curseforge(content: &str, folder: &Path, api_url: &str, api_key: Option<&str>) -> io::Result<Manifest> {
+    let manifest: CurseManifest = serde_json::from_str(content).map_err(io::Error::other)?;
+
+    let file_ids: Vec<u32> = manifest.files.iter().map(|file| file.file_id).collect();
+    let api_files = fetch_files(api_url, api_key, &file_ids)?;
+
+    let mut files: Vec<ManifestFile> = Vec::new();
+    for file in &manifest.files {
+        let api_file = api_files.iter().find(|api_file| api_file.id == file.file_id).ok_or_else(|| io::Error::new(
+            io::ErrorKind::NotFound, format!("CurseForge file {} of project {} not found", file.file_id, file.project_id)))?;
+        let url = api_file.download_url.clone().ok_or_else(|| io::Error::new(
+            io::ErrorKind::PermissionDenied, format!("{} can't be downloaded outside of CurseForge", api_file.file_name)))?;
+        files.push(ManifestFile {
+            path: api_file.file_name.clone(),
+            category: Some(Category::Mod),
+            url,
+            hashes: api_file.hashes.iter()
+                .filter(|hash| hash.algo == SHA1_ALGO)
+                .map(|hash| ("sha1".to_string(), hash.value.clone()))
+                .collect(),
+            size: api_file.file_length,
+            optional: !file.required,
+            ..Default::default()
+        });
+    }
+
+    let modloader = manifest.minecraft.mod_loaders.iter()
+        .find(|loader| loader.primary)
+        .or(manifest.minecraft.mod_loaders.first())
+        .and_then(|loader| parse_mod_loader(&loader.id));
+
+    Ok(Manifest {
+        files,
+        overrides: vec![folder.join(&manifest.overrides)],
+        minecraft_version: Some(manifest.minecraft.version),
+        modloader,
+    })
+}
+
+/// Fetches the download urls and hashes of `file_ids` in one request.
+fn fetch_files(api_url: &str, api_key: Option<&str>, file_ids: &[u32]) -> io::Result<Vec<ApiFile>> {
+    let mut request = http::post(&format!("{}/v1/mods/files", api_url.trim_end_matches('/')));
+    if let Some(api_key) = api_key {
+        request = request.set("x-api-key", api_key);
+    }
+    let body = json!({ "fileIds": file_ids }).to_string();
+    let response = request.set("Content-Type", "application/json").send_string(&body).map_err(io::Error::other)?;
+    let response: ApiResponse<Vec<ApiFile>> = serde_json::from_str(&response.into_string()?).map_err(io::Error::other)?;
+    Ok(response.data)
+}
+
+/// Parses a modloader id as `forge-47.1.0` or `fabric-0.14.21`.
+fn parse_mod_loader(id: &str) -> Option<(ModloaderKind, String)> {
+    let (kind, version) = id.split_once('-')?;
+    Some((ModloaderKind::from(kind)?, version.to_string()))
+}
@@ -0,0 +1,347 @@
+use std::collections::HashMap;
+use std::fs::{self, File, create_dir_all};
+use std::io::{self, Read};
+use std::path::{Component, Path};
+
+use zip::ZipArchive;
+
+use crate::files::{list_files, format_size};
+
+/// What a modpack may write in the instance folder when no `extract.allowed` is configured.
+pub const DEFAULT_ALLOWED: &[&str] = &[
+    "mods", "config", "defaultconfigs", "resourcepacks", "shaderpacks", "datapacks", "kubejs", "scripts",
+    "options.txt", "optionsof.txt", "optionsshaders.txt", "servers.dat",
+];
+pub const DEFAULT_MAX_FILES: usize = 10_000;
+/// In Mo.
+pub const DEFAULT_MAX_SIZE: u64 = 4096;
+
+/// Unix file type bits of a zip entry, and those of a symlink.
+const FILE_TYPE_MASK: u32 = 0o170000;
+const SYMLINK_TYPE: u32 = 0o120000;
+pub const SYMLINK_REASON: &str = "lien symbolique"; //lang
+
+/// What an archive may extract, so a malicious or mistaken modpack can't write outside of its folders
+/// (e.g. over `launcher_profiles.json`) or fill the disk.
+#[derive(Debug, Clone)]
+pub struct ExtractPolicy {
+    /// Top-level folders and files the modpack may write in the instance folder, empty for any.
+    pub allowed: Vec<String>,
+    pub max_files: usize,
+    /// Total size of the extracted files, in bytes.
+    pub max_size: u64,
+}
+
+/// An archive entry left out by the policy, and why.
+#[derive(Debug, Clone)]
+pub struct Rejected {
+    pub path: String,
+    pub reason: String,
+}
+
+impl ExtractPolicy {
+    pub fn from(config: &HashMap<String, String>) -> ExtractPolicy {
+        let allowed = match config.get("extract.allowed").map(String::as_str) {
+            Some("*") => Vec::new(),
+            Some(allowed) => allowed.split(',').map(str::trim).filter(|name| !name.is_empty()).map(String::from).collect(),
+            None => DEFAULT_ALLOWED.iter().map(|name| name.to_string()).collect(),
+        };
+        ExtractPolicy {
+            allowed,
+            max_files: config.get("extract.max_files")
+                .map(|count| count.parse().expect("extract.max_files must be a number"))
+                .unwrap_or(DEFAULT_MAX_FILES),
+            max_size: config.get("extract.max_size")
+                .map(|size| size.parse().expect("extract.max_size must be a number of Mo"))
+                .unwrap_or(DEFAULT_MAX_SIZE) * 1024 * 1024,
+        }
+    }
+
+    /// The same limits for an archive extracted next to the installer rather than in the instance,
+    /// its content being checked again when copied, see `copy_folder_checked`.
+    pub fn without_allow_list(&self) -> ExtractPolicy {
+        ExtractPolicy { allowed: Vec::new(), ..self.clone() }
+    }
+
+    /// Why `path`, relative to the extraction folder, can't be written, `None` if it can.
+    pub fn check_path(&self, path: &str) -> Option<String> {
+        let is_drive = path.split(['/', '\\']).next().is_some_and(|first| first.contains(':'));
+        let safe = !is_drive && Path::new(&path.replace('\\', "/")).components()
+            .all(|component| matches!(component, Component::Normal(_)));
+        if !safe {
+            return Some("chemin hors du dossier".to_string()); //lang
+        }
+        let top = path.split(['/', '\\']).next().unwrap_or_default();
+        if !self.allowed.is_empty() && !self.allowed.iter().any(|allowed| allowed.eq_ignore_ascii_case(top)) {
+            return Some(format!("{} n'est pas autorisé", top)); //lang
+        }
+        None
+    }
+}
+
+/// Whether a zip entry with the unix mode `mode` is a symlink.
+pub fn is_symlink(mode: Option<u32>) -> bool {
+    mode.is_some_and(|mode| mode & FILE_TYPE_MASK == SYMLINK_TYPE)
+}
+
+fn limit_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Extracts the zip `archive` into `folder` following `policy`. Entries with unsafe paths, symlinks and
+/// entries outside the allowed folders are skipped and returned. Going over the limits stops the extraction.
+pub fn extract_archive(archive: &Path, folder: &Path, policy: &ExtractPolicy) -> io::Result<Vec<Rejected>> {
+    let mut zip = ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+    let (accepted, rejected) = scan_archive(&mut zip, policy)?;
+    read_entries(&mut zip, &accepted, policy, Some(folder))?;
+    Ok(rejected)
+}
+
+/// Reads the whole zip `archive` without writing anything, failing where `extract_archive` would:
+/// over the limits of `policy`, or corrupted. Lets a reinstall check the archive before wiping the old files.
+pub fn check_archive(archive: &Path, policy: &ExtractPolicy) -> io::Result<()> {
+    let mut zip = ZipArchive::new(File::open(archive)?).map_err(io::Error::other)?;
+    let (accepted, _) = scan_archive(&mut zip, policy)?;
+    read_entries(&mut zip, &accepted, policy, None)
+}
+
+/// Indexes of the entries of `zip` to extract and the entries left out, refusing archives over the limits
+/// of `policy` from the sizes they declare.
+fn scan_archive(zip: &mut ZipArchive<File>, policy: &ExtractPolicy) -> io::Result<(Vec<usize>, Vec<Rejected>)> {
+    let mut rejected: Vec<Rejected> = Vec::new();
+    let mut accepted: Vec<usize> = Vec::new();
+    let mut declared_size: u64 = 0;
+
+    for index in 0..zip.len() {
+        let entry = zip.by_index_raw(index).map_err(io::Error::other)?;
+        let name = entry.name().to_string();
+        let reason = if is_symlink(entry.unix_mode()) {
+            Some(SYMLINK_REASON.to_string())
+        } else {
+            policy.check_path(name.trim_end_matches('/'))
+        };
+        match reason {
+            Some(reason) => rejected.push(Rejected { path: name, reason }),
+            None if entry.is_dir() => {}
+            None => {
+                declared_size += entry.size();
+                accepted.push(index);
+            }
+        }
+    }
+    if accepted.len() > policy.max_files {
+        return Err(limit_error(format!("l'archive contient trop de fichiers ({} pour {} autorisés)", accepted.len(), policy.max_files))); //lang
+    }
+    if declared_size > policy.max_size {
+        return Err(limit_error(format!("l'archive est trop volumineuse ({} pour {} autorisés)", format_size(declared_size), format_size(policy.max_size)))); //lang
+    }
+    Ok((accepted, rejected))
+}
+
+/// Decompresses the `accepted` entries of `zip` into `folder`, or only reads them when there is no folder.
+fn read_entries(zip: &mut ZipArchive<File>, accepted: &[usize], policy: &ExtractPolicy, folder: Option<&Path>) -> io::Result<()> {
+    // the sizes written in the archive can lie, so the extracted bytes are counted too
+    let mut remaining = policy.max_size;
+    for &index in accepted {
+        let entry = zip.by_index(index).map_err(io::Error::other)?;
+        let written = match folder {
+            Some(folder) => {
+                let target = folder.join(entry.name().replace('\\', "/"));
+                if let Some(parent) = target.parent() {
+                    create_dir_all(parent)?;
+                }
+                io::copy(&mut entry.take(remaining + 1), &mut File::create(&target)?)?
+            }
+            None => io::copy(&mut entry.take(remaining + 1), &mut io::sink())?,
+        };
+        if written > remaining {
+            return Err(limit_error(format!("l'archive est plus volumineuse qu'annoncé (plus de {})", format_size(policy.max_size)))); //lang
+        }
+        remaining -= written;
+    }
+    Ok(())
+}
+
+/// Copies the content of `from` into `to` following `policy`, replacing the files already there.
+/// Returns the files left out.
+pub fn copy_folder_checked(from: &Path, to: &Path, policy: &ExtractPolicy) -> io::Result<Vec<Rejected>> {
+    let (accepted, rejected) = scan_folder(from, policy)?;
+    for file in accepted {
+        let target = to.join(&file);
+        if let Some(folder) = target.parent() {
+            create_dir_all(folder)?;
+        }
+        fs::copy(from.join(&file), target)?;
+    }
+    Ok(rejected)
+}
+
+/// Fails where `copy_folder_checked` would refuse `from` before copying anything.
+pub fn check_folder(from: &Path, policy: &ExtractPolicy) -> io::Result<()> {
+    scan_folder(from, policy).map(|_| ())
+}
+
+/// Files of `from` to copy and the files left out, refusing folders over the limits of `policy`.
+fn scan_folder(from: &Path, policy: &ExtractPolicy) -> io::Result<(Vec<String>, Vec<Rejected>)> {
+    let mut rejected: Vec<Rejected> = Vec::new();
+    let mut accepted: Vec<String> = Vec::new();
+    let mut size: u64 = 0;
+    for file in list_files(from)? {
+        let metadata = fs::symlink_metadata(from.join(&file))?;
+        let reason = if metadata.is_symlink() {
+            Some(SYMLINK_REASON.to_string())
+        } else {
+            policy.check_path(&file)
+        };
+        match reason {
+            Some(reason) => rejected.push(Rejected { path: file, reason }),
+            None => {
+                size += metadata.len();
+                accepted.push(file);
+            }
+        }
+    }
+    if accepted.len() > policy.max_files {
+        return Err(limit_error(format!("le dossier contient trop de fichiers ({} pour {} autorisés)", accepted.len(), policy.max_files))); //lang
+    }
+    if size > policy.max_size {
+        return Err(limit_error(format!("le dossier est trop volumineux ({} pour {} autorisés)", format_size(size), format_size(policy.max_size)))); //lang
+    }
+    Ok((accepted, rejected))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::io::Write;
+    use std::path::PathBuf;
+
+    use zip::ZipWriter;
+    use zip::write::SimpleFileOptions;
+
+    use super::*;
+
+    fn test_folder(name: &str) -> PathBuf {
+        let folder = env::temp_dir().join(format!("magic_installer_extract_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    fn write_zip(path: &Path, files: &[(&str, &[u8])], symlinks: &[(&str, &str)]) {
+        let mut zip = ZipWriter::new(File::create(path).unwrap());
+        for (name, content) in files {
+            zip.start_file(*name, SimpleFileOptions::default()).unwrap();
+            zip.write_all(content).unwrap();
+        }
+        for (name, target) in symlinks {
+            zip.add_symlink(*name, *target, SimpleFileOptions::default()).unwrap();
+        }
+        zip.finish().unwrap();
+    }
+
+    fn default_policy() -> ExtractPolicy {
+        ExtractPolicy::from(&HashMap::new())
+    }
+
+    #[test]
+    fn check_path_allows_listed_folders() {
+        let policy = default_policy();
+        assert_eq!(policy.check_path("mods/sodium.jar"), None);
+        assert_eq!(policy.check_path("config/sodium/options.json"), None);
+        assert_eq!(policy.check_path("options.txt"), None);
+        assert_eq!(policy.check_path("MODS\\iris.jar"), None);
+    }
+
+    #[test]
+    fn check_path_rejects_other_top_level_files() {
+        let policy = default_policy();
+        assert!(policy.check_path("launcher_profiles.json").is_some());
+        assert!(policy.check_path("versions/1.20.1/1.20.1.json").is_some());
+    }
+
+    #[test]
+    fn check_path_rejects_paths_leaving_the_folder() {
+        let policy = ExtractPolicy::from(&HashMap::from([("extract.allowed".to_string(), "*".to_string())]));
+        assert!(policy.allowed.is_empty());
+        assert_eq!(policy.check_path("anything/file.txt"), None);
+        for path in ["../evil.jar", "mods/../../evil.jar", "/etc/passwd", "C:\\Windows\\evil.dll", "C:evil.dll", "mods\\..\\..\\evil.jar"] {
+            assert!(policy.check_path(path).is_some(), "{} should be rejected", path);
+        }
+    }
+
+    #[test]
+    fn policy_from_config() {
+        let config = HashMap::from([
+            ("extract.allowed".to_string(), "mods, config ,".to_string()),
+            ("extract.max_files".to_string(), "12".to_string()),
+            ("extract.max_size".to_string(), "3".to_string()),
+        ]);
+        let policy = ExtractPolicy::from(&config);
+        assert_eq!(policy.allowed, vec!["mods", "config"]);
+        assert_eq!(policy.max_files, 12);
+        assert_eq!(policy.max_size, 3 * 1024 * 1024);
+        assert!(policy.without_allow_list().allowed.is_empty());
+    }
+
+    #[test]
+    fn extract_archive_skips_rejected_entries() {
+        let folder = test_folder("skips");
+        let archive = folder.join("pack.zip");
+        write_zip(&archive, &[
+            ("mods/sodium.jar", b"sodium"),
+            ("launcher_profiles.json", b"{}"),
+            ("../evil.jar", b"evil"),
+        ], &[("mods/link.jar", "/etc/passwd")]);
+        let target = folder.join("instance");
+
+        let rejected = extract_archive(&archive, &target, &default_policy()).unwrap();
+        let mut rejected: Vec<&str> = rejected.iter().map(|entry| entry.path.as_str()).collect();
+        rejected.sort();
+        assert_eq!(rejected, vec!["../evil.jar", "launcher_profiles.json", "mods/link.jar"]);
+        assert_eq!(fs::read(target.join("mods/sodium.jar")).unwrap(), b"sodium");
+        assert!(!target.join("launcher_profiles.json").exists());
+        assert!(!target.join("mods/link.jar").exists());
+        assert!(!folder.join("evil.jar").exists());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn extract_archive_limits() {
+        let folder = test_folder("limits");
+        let archive = folder.join("pack.zip");
+        write_zip(&archive, &[("mods/a.jar", &[0; 600]), ("mods/b.jar", &[0; 600]), ("mods/c.jar", &[0; 600])], &[]);
+        let target = folder.join("instance");
+
+        let too_many = ExtractPolicy { max_files: 2, ..default_policy() };
+        assert!(extract_archive(&archive, &target, &too_many).is_err());
+        let too_big = ExtractPolicy { max_size: 1000, ..default_policy() };
+        assert!(extract_archive(&archive, &target, &too_big).is_err());
+        assert!(!target.join("mods").exists());
+
+        let enough = ExtractPolicy { max_files: 3, max_size: 1800, ..default_policy() };
+        assert!(extract_archive(&archive, &target, &enough).unwrap().is_empty());
+        assert_eq!(list_files(&target.join("mods")).unwrap().len(), 3);
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn check_archive_reads_without_writing() {
+        let folder = test_folder("check");
+        let archive = folder.join("pack.zip");
+        let content = b"content of a mod".repeat(40);
+        write_zip(&archive, &[("mods/a.jar", &content), ("mods/b.jar", &[0; 600])], &[]);
+
+        assert!(check_archive(&archive, &default_policy()).is_ok());
+        assert!(check_archive(&archive, &ExtractPolicy { max_files: 1, ..default_policy() }).is_err());
+        assert!(check_archive(&archive, &ExtractPolicy { max_size: 1000, ..default_policy() }).is_err());
+        assert_eq!(list_files(&folder).unwrap(), vec!["pack.zip"]);
+
+        // the data of mods/a.jar starts after its 30 bytes header and its name
+        let mut bytes = fs::read(&archive).unwrap();
+        bytes[42] ^= 0xff;
+        fs::write(&archive, bytes).unwrap();
+        assert!(check_archive(&archive, &default_policy()).is_err());
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -1,46 +1,90 @@
-use std::process::Command;
+use std::collections::HashMap;
+use std::process::{Command, Output};
 use std::path::Path;
-use std::fs::{File, create_dir_all};
+use std::fs::{self, File, create_dir_all};
 use std::io::{Write, Read, self};
 use std::sync::mpsc;
-use ureq;
+use std::time::{Duration, Instant};
+
+use crate::http;
+use crate::rate_limit::{rate_limit, throttle};
+
+use sha1::Sha1;
+use sha2::{Digest, Sha256, Sha512};
+
+/// Below this rate, in bytes per second, a download switches to the next mirror.
+const MIN_DOWNLOAD_RATE: f32 = 50_000.0;
+/// Time given to a download before its rate is checked.
+const SLOW_DOWNLOAD_DELAY: Duration = Duration::from_secs(10);
 
 /// Downloads a file, saves it to the specified path and sends the download status through a channel.
 /// the `DownloadStatus::Downloading(f32)` is a float between 0 and 1, representing the percentage of the file downloaded.
-/// send `DownloadStatus::Downloaded` when the download is finished.
-pub fn download_file(path: &str, url: &str, tx: mpsc::Sender<DownloadStatus>) -> io::Result<()> {
+/// send `DownloadStatus::Downloaded` with the url used when the download is finished.
+/// `urls` are mirrors of the same file tried in order, the next one is used when a download fails or is too slow.
+pub fn download_file(path: &str, urls: &[String], tx: mpsc::Sender<DownloadStatus>) -> io::Result<()> {
+    let mut error = String::new();
+    for (index, url) in urls.iter().enumerate() {
+        if index > 0 {
+            tx.send(DownloadStatus::Mirror(url.clone())).unwrap();
+        }
+        // the last mirror is kept even if slow, there is nothing else to try
+        let check_rate = index + 1 < urls.len();
+        match download_from(path, url, check_rate, &tx) {
+            Ok(()) => {
+                tx.send(DownloadStatus::Downloaded(url.clone())).unwrap();
+                return Ok(());
+            }
+            Err(err) => error = err,
+        }
+    }
+    tx.send(DownloadStatus::Error(error)).unwrap();
+    Ok(())
+}
+
+fn download_from(path: &str, url: &str, check_rate: bool, tx: &mpsc::Sender<DownloadStatus>) -> Result<(), String> {
     let mut buffer: Vec<u8> = vec![0; 4096];
-    let mut file: File = File::create(path)?;
-    
-    let response = match ureq::get(url).call() {
-        Ok(response) => response,
-        Err(err) => {
-            tx.send(DownloadStatus::Error(err)).unwrap();
-            return Ok(());
-        } 
-    };
-    let length = response.header("Content-Length").unwrap().parse::<f32>().unwrap();
+    let response = http::get(url).call().map_err(|err| err.to_string())?;
+    // share pages answer with html instead of the file when the link is not a direct download
+    if response.content_type() == "text/html" {
+        return Err("le lien n'est pas un téléchargement direct".to_string()); //lang
+    }
+    let mut file: File = File::create(path).map_err(|err| err.to_string())?;
+    let length = response.header("Content-Length").and_then(|length| length.parse::<f32>().ok());
     let mut stream = response.into_reader();
+    let start = Instant::now();
+    let mut downloaded: usize = 0;
     
     loop {
-        let bytes_read: usize = stream.read(&mut buffer)?;
-        file.write_all(&buffer[..bytes_read])?;
-        tx.send(DownloadStatus::Downloading (file.metadata().unwrap().len() as f32 / length as f32)).unwrap();
+        let bytes_read: usize = stream.read(&mut buffer).map_err(|err| err.to_string())?;
+        file.write_all(&buffer[..bytes_read]).map_err(|err| err.to_string())?;
+        downloaded += bytes_read;
+        throttle(bytes_read);
+        tx.send(DownloadStatus::Received(bytes_read)).unwrap();
+        let percentage = length.map_or(0.0, |length| downloaded as f32 / length);
+        tx.send(DownloadStatus::Downloading (percentage)).unwrap();
         if bytes_read == 0 {
             break;
         }
+        let elapsed = start.elapsed();
+        // a download slowed down on purpose is not a slow mirror
+        if check_rate && rate_limit() == 0 && elapsed > SLOW_DOWNLOAD_DELAY && (downloaded as f32 / elapsed.as_secs_f32()) < MIN_DOWNLOAD_RATE {
+            return Err("téléchargement trop lent".to_string()); //lang
+        }
     }
-    tx.send(DownloadStatus::Downloaded).unwrap();
     Ok(())
 }
 
 pub enum DownloadStatus{
-    Error (ureq::Error),
+    Error (String),
     Downloading (f32),
-    Downloaded,
+    /// Bytes received since the previous status.
+    Received (usize),
+    /// The previous mirror failed, the download restarts from this url.
+    Mirror (String),
+    Downloaded (String),
 } 
 
-#[allow(dead_code)]
+#[allow(dead_code, clippy::enum_variant_names)]
 pub enum FileStatus {
     FileExists,
     FileDoesntExist,
@@ -56,29 +100,103 @@ pub fn create_folder(path: &str) -> FileStatus{
     FileStatus::FileExists
 }
 
-/// Unzip a file to a folder
-/// extracts the `filename` in the `folderpath`
-pub fn unzip_file(filename: &str, folderpath: &str) -> Result<(), io::Error> {
-    let mut cmd = Command::new("tar");
-    cmd.current_dir(&Path::new(folderpath));
-    cmd.arg("-xf").arg(filename);
-
-    match cmd.spawn() {
-        Ok(mut child) => {
-            let status = child.wait().expect("Failed to wait for the commands");
-            if status.success() {
-                Ok(())
-            } else {
-                Err(io::Error::new(io::ErrorKind::Other, "Failed to unzip file"))
+/// Runs the fabric/forge installer with `args` and waits for it to exit, capturing its stdout and stderr.
+/// `.jar` installers are run with `java -jar`, anything else is executed directly.
+pub fn run_installer(filepath: &str, args: &[String], java: Option<&Path>) -> io::Result<Output> {
+    let mut cmd = if filepath.ends_with(".jar") {
+        let java = match java {
+            Some(java) => java,
+            None => return Err(io::Error::new(io::ErrorKind::NotFound, "Java introuvable")), //lang
+        };
+        let mut cmd = Command::new(java);
+        cmd.arg("-jar").arg(filepath);
+        cmd
+    } else {
+        Command::new(filepath)
+    };
+    if let Some(folder) = Path::new(filepath).parent() {
+        cmd.current_dir(folder);
+    }
+    cmd.args(args).output()
+}
+
+/// Fetches `url` and returns the response body as a string, used for small JSON documents.
+pub fn fetch_string(url: &str) -> io::Result<String> {
+    match http::get(url).call() {
+        Ok(response) => response.into_string(),
+        Err(err) => Err(io::Error::other(err.to_string())),
+    }
+}
+
+/// Whether `source` is a web url rather than a local path.
+pub fn is_url(source: &str) -> bool {
+    source.starts_with("http://") || source.starts_with("https://")
+}
+
+/// Hex digest of `content` with the algorithm named `algorithm` (`sha1`, `sha256` or `sha512`).
+/// Returns `None` for an unsupported algorithm.
+pub fn bytes_hash(content: &[u8], algorithm: &str) -> Option<String> {
+    match algorithm {
+        "sha1" => Some(format!("{:x}", Sha1::digest(content))),
+        "sha256" => Some(format!("{:x}", Sha256::digest(content))),
+        "sha512" => Some(format!("{:x}", Sha512::digest(content))),
+        _ => None,
+    }
+}
+
+pub fn file_hash(path: &Path, algorithm: &str) -> io::Result<Option<String>> {
+    Ok(bytes_hash(&fs::read(path)?, algorithm))
+}
+
+/// Whether the file matches `hashes`, `false` if none of them can be checked.
+pub fn is_unchanged(path: &Path, hashes: &HashMap<String, String>) -> bool {
+    has_supported_hash(hashes) && path.is_file() && verify_hashes(path, hashes).is_ok()
+}
+
+/// Whether at least one of `hashes` can be checked.
+pub fn has_supported_hash(hashes: &HashMap<String, String>) -> bool {
+    hashes.keys().any(|algorithm| bytes_hash(&[], algorithm).is_some())
+}
+
+/// Checks a file against the expected `hashes`, keyed by algorithm. Unsupported algorithms are skipped.
+pub fn verify_hashes(path: &Path, hashes: &HashMap<String, String>) -> io::Result<()> {
+    for (algorithm, expected) in hashes {
+        if let Some(hash) = file_hash(path, algorithm)? {
+            if !hash.eq_ignore_ascii_case(expected) {
+                return Err(io::Error::new(io::ErrorKind::InvalidData,
+                    format!("{} : empreinte {} invalide", path.display(), algorithm))); //lang
             }
         }
-        Err(err) => Err(err),
     }
+    Ok(())
+}
+
+/// Lists the files in `folder` and its subfolders, as paths relative to `folder` separated with `/`.
+pub fn list_files(folder: &Path) -> io::Result<Vec<String>> {
+    let mut files: Vec<String> = Vec::new();
+    if !folder.exists() {
+        return Ok(files);
+    }
+    for entry in fs::read_dir(folder)? {
+        let entry = entry?;
+        let name = entry.file_name().to_string_lossy().to_string();
+        if entry.file_type()?.is_dir() {
+            files.extend(list_files(&entry.path())?.into_iter().map(|file| format!("{}/{}", name, file)));
+        } else {
+            files.push(name);
+        }
+    }
+    Ok(files)
 }
 
-/// Launch an executable in a new process, used for launching the fabric/forge installer.
-pub fn launch_executable(filepath: &str) {
-    Command::new(filepath)
-        .spawn()
-        .expect("Failed to execute process");
-}
\ No newline at end of file
+/// Size in a readable unit, e.g. `12.3 Mo`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: &[&str] = &["o", "Ko", "Mo", "Go"]; //lang
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {format!("{} {}", bytes, UNITS[0])} else {format!("{:.1} {}", size, UNITS[unit])}
+}
@@ -0,0 +1,169 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::File;
+use std::io::{BufReader, self};
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use rustls::{Certificate, ClientConfig, OwnedTrustAnchor, RootCertStore};
+use ureq::{Agent, AgentBuilder, Proxy, Request};
+
+use crate::config::VERSION;
+
+pub const DEFAULT_CONNECT_TIMEOUT: u64 = 10;
+pub const DEFAULT_READ_TIMEOUT: u64 = 30;
+
+/// How the installer reaches the network, from the `http.` keys of the config and the proxy environment variables.
+#[derive(Debug, Clone)]
+pub struct HttpSettings {
+    /// Proxy url, `http.proxy` or else `HTTPS_PROXY`/`HTTP_PROXY`.
+    pub proxy: Option<String>,
+    /// Hosts reached without the proxy, `http.no_proxy` or else `NO_PROXY`.
+    pub no_proxy: Vec<String>,
+    pub connect_timeout: Duration,
+    pub read_timeout: Duration,
+    /// PEM files of certificate authorities trusted on top of the usual ones, e.g. for a mirror with a private CA.
+    pub ca_certificates: Vec<String>,
+}
+
+/// The agents shared by every request, one going through the proxy and one for the hosts of `no_proxy`.
+struct Agents {
+    direct: Agent,
+    proxied: Option<Agent>,
+    no_proxy: Vec<String>,
+}
+
+static AGENTS: OnceLock<Agents> = OnceLock::new();
+
+impl HttpSettings {
+    pub fn from(config: &HashMap<String, String>) -> HttpSettings {
+        let proxy = config.get("http.proxy").cloned()
+            .or(env_var(&["HTTPS_PROXY", "https_proxy", "HTTP_PROXY", "http_proxy", "ALL_PROXY", "all_proxy"]))
+            .filter(|proxy| !proxy.is_empty());
+        let no_proxy = config.get("http.no_proxy").cloned()
+            .or(env_var(&["NO_PROXY", "no_proxy"]))
+            .map(|hosts| hosts.split(',').map(|host| host.trim().to_lowercase()).filter(|host| !host.is_empty()).collect())
+            .unwrap_or_default();
+        let seconds = |key: &str, default: u64| Duration::from_secs(config.get(key)
+            .map(|seconds| seconds.parse().unwrap_or_else(|_| panic!("{} must be a number of seconds", key)))
+            .unwrap_or(default));
+
+        HttpSettings {
+            proxy,
+            no_proxy,
+            connect_timeout: seconds("http.connect_timeout", DEFAULT_CONNECT_TIMEOUT),
+            read_timeout: seconds("http.read_timeout", DEFAULT_READ_TIMEOUT),
+            ca_certificates: config.get("http.ca_certificates")
+                .map(|paths| paths.split(',').map(|path| path.trim().to_string()).filter(|path| !path.is_empty()).collect())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+/// Builds the shared agents, to call once before any request.
+pub fn init(settings: &HttpSettings) -> io::Result<()> {
+    let tls_config = Arc::new(tls_config(&settings.ca_certificates)?);
+    let builder = || AgentBuilder::new()
+        .timeout_connect(settings.connect_timeout)
+        .timeout_read(settings.read_timeout)
+        .user_agent(&format!("magic-installer/{}", VERSION))
+        .tls_config(Arc::clone(&tls_config));
+
+    let proxied = match &settings.proxy {
+        Some(proxy) => Some(builder().proxy(Proxy::new(proxy).map_err(io::Error::other)?).build()),
+        None => None,
+    };
+    let agents = Agents { direct: builder().build(), proxied, no_proxy: settings.no_proxy.clone() };
+    // a second call keeps the first agents
+    let _ = AGENTS.set(agents);
+    Ok(())
+}
+
+pub fn get(url: &str) -> Request {
+    agent(url).get(url)
+}
+
+pub fn post(url: &str) -> Request {
+    agent(url).post(url)
+}
+
+pub fn head(url: &str) -> Request {
+    agent(url).head(url)
+}
+
+/// The agent for `url`, going through the proxy unless its host is in `no_proxy`.
+fn agent(url: &str) -> Agent {
+    let agents = AGENTS.get_or_init(|| Agents { direct: AgentBuilder::new().build(), proxied: None, no_proxy: Vec::new() });
+    let authority = url.split_once("://").map_or(url, |(_, rest)| rest).split(['/', '?', '#']).next().unwrap_or_default();
+    let host = match authority.rsplit_once(':') {
+        Some((host, port)) if port.chars().all(|c| c.is_ascii_digit()) => host,
+        _ => authority,
+    };
+    match &agents.proxied {
+        Some(proxied) if !is_no_proxy(&host.to_lowercase(), &agents.no_proxy) => proxied.clone(),
+        _ => agents.direct.clone(),
+    }
+}
+
+/// Whether `host` matches an entry of `no_proxy`: `*`, the host itself or one of its parent domains.
+fn is_no_proxy(host: &str, no_proxy: &[String]) -> bool {
+    no_proxy.iter().any(|entry| {
+        let entry = entry.trim_start_matches('.');
+        entry == "*" || host == entry || host.ends_with(&format!(".{}", entry))
+    })
+}
+
+/// The usual certificate authorities, with the ones of the PEM files `ca_certificates`.
+fn tls_config(ca_certificates: &[String]) -> io::Result<ClientConfig> {
+    let mut roots = RootCertStore::empty();
+    roots.add_trust_anchors(webpki_roots::TLS_SERVER_ROOTS.iter().map(|anchor| {
+        OwnedTrustAnchor::from_subject_spki_name_constraints(anchor.subject, anchor.spki, anchor.name_constraints)
+    }));
+    for path in ca_certificates {
+        let mut reader = BufReader::new(File::open(path)?);
+        for certificate in rustls_pemfile::certs(&mut reader)? {
+            roots.add(&Certificate(certificate)).map_err(io::Error::other)?;
+        }
+    }
+    Ok(ClientConfig::builder()
+        .with_safe_defaults()
+        .with_root_certificates(roots)
+        .with_no_client_auth())
+}
+
+fn env_var(names: &[&str]) -> Option<String> {
+    names.iter().find_map(|name| env::var(name).ok())
+}
+
+#[cfg(test)]
+pub mod tests {
+    use std::io::{BufRead, BufReader, Write};
+    use std::net::TcpListener;
+    use std::thread;
+
+    /// Serves `files` on a local port until the tests end, other paths answer 404. Returns the url of the server.
+    pub fn serve(files: Vec<(&'static str, Vec<u8>)>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(&stream);
+                let mut request = String::new();
+                let _ = reader.read_line(&mut request);
+                let mut line = String::new();
+                while reader.read_line(&mut line).is_ok_and(|read| read > 2) {
+                    line.clear();
+                }
+                let path = request.split_whitespace().nth(1).unwrap_or_default();
+                let _ = match files.iter().find(|(file, _)| *file == path) {
+                    Some((_, content)) => {
+                        write!(stream, "HTTP/1.1 200 OK\r\nContent-Type: application/octet-stream\r\nContent-Length: {}\r\nConnection: close\r\n\r\n", content.len())
+                            .and_then(|_| stream.write_all(content))
+                    }
+                    None => write!(stream, "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n"),
+                };
+            }
+        });
+        url
+    }
+}
@@ -0,0 +1,208 @@
+use std::cmp::Reverse;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+#[cfg(windows)]
+const JAVA_EXECUTABLE: &str = "java.exe";
+#[cfg(not(windows))]
+const JAVA_EXECUTABLE: &str = "java";
+
+/// Folders containing one java installation per subfolder, e.g. `C:\Program Files\Java\jdk-17\`.
+#[cfg(windows)]
+const JAVA_INSTALL_FOLDERS: &[&str] = &[
+    "%programfiles%\\Java\\",
+    "%programfiles%\\Eclipse Adoptium\\",
+    "%programfiles%\\Microsoft\\",
+    "%programfiles%\\Zulu\\",
+    "%programfiles%\\BellSoft\\",
+    "%programfiles(x86)%\\Java\\",
+];
+#[cfg(not(windows))]
+const JAVA_INSTALL_FOLDERS: &[&str] = &[
+    "/usr/lib/jvm/",
+    "/Library/Java/JavaVirtualMachines/",
+];
+
+#[derive(Debug, Clone)]
+pub struct JavaInstall {
+    pub path: PathBuf,
+    pub version: String,
+    pub major: u32,
+}
+
+#[derive(Debug)]
+pub enum JavaStatus {
+    /// A java matching the required version, or the most recent one if no version is required.
+    Found(JavaInstall),
+    /// Java is installed but none matches the required version, holds the closest one.
+    WrongVersion(JavaInstall),
+    Missing,
+}
+
+impl JavaStatus {
+    pub fn install(&self) -> Option<&JavaInstall> {
+        match self {
+            JavaStatus::Found(install) | JavaStatus::WrongVersion(install) => Some(install),
+            JavaStatus::Missing => None,
+        }
+    }
+}
+
+/// Looks for java in `JAVA_HOME`, the `PATH`, the runtimes bundled with the Minecraft launcher and the common install folders.
+pub fn find_java_installs(minecraft_folder: &str) -> Vec<JavaInstall> {
+    let mut installs: Vec<JavaInstall> = Vec::new();
+    for path in java_candidates(minecraft_folder) {
+        let path = fs::canonicalize(&path).unwrap_or(path);
+        if installs.iter().any(|install| install.path == path) {
+            continue;
+        }
+        if let Some((version, major)) = java_version(&path) {
+            installs.push(JavaInstall { path, version, major });
+        }
+    }
+    installs
+}
+
+/// The java versions a modpack runs on: `java_version`, up to `java_max_version` when set.
+/// Old Minecraft and Forge versions break on newer java, so only the exact version matches by default.
+#[derive(Debug, Clone, Copy)]
+pub struct JavaRequirement {
+    pub min: u32,
+    pub max: u32,
+}
+
+impl JavaRequirement {
+    pub fn matches(&self, major: u32) -> bool {
+        (self.min..=self.max).contains(&major)
+    }
+
+    /// The required versions as shown to the player, e.g. `17` or `17 à 21`.
+    pub fn label(&self) -> String {
+        if self.min == self.max {self.min.to_string()} else {format!("{} à {}", self.min, self.max)} //lang
+    }
+}
+
+/// Picks the java matching `required`, the most recent one in its range.
+/// When none matches, the closest one is returned as `WrongVersion`, to run an installer anyway.
+pub fn select_java(installs: &[JavaInstall], required: Option<JavaRequirement>) -> JavaStatus {
+    let required = match required {
+        Some(required) => required,
+        None => return installs.iter().max_by_key(|install| install.major).cloned().map_or(JavaStatus::Missing, JavaStatus::Found),
+    };
+
+    let matching = installs.iter()
+        .filter(|install| required.matches(install.major))
+        .max_by_key(|install| install.major);
+    // on a tie the newer one is picked, installers cope better with a newer java than an older one
+    let closest = installs.iter()
+        .min_by_key(|install| (install.major.abs_diff(required.min).min(install.major.abs_diff(required.max)), Reverse(install.major)));
+    match (matching, closest) {
+        (Some(install), _) => JavaStatus::Found(install.clone()),
+        (None, Some(install)) => JavaStatus::WrongVersion(install.clone()),
+        (None, None) => JavaStatus::Missing,
+    }
+}
+
+fn java_candidates(minecraft_folder: &str) -> Vec<PathBuf> {
+    let mut candidates: Vec<PathBuf> = Vec::new();
+    if let Some(java_home) = env::var_os("JAVA_HOME") {
+        candidates.push(Path::new(&java_home).join("bin").join(JAVA_EXECUTABLE));
+    }
+    if let Some(path) = env::var_os("PATH") {
+        candidates.extend(env::split_paths(&path).map(|folder| folder.join(JAVA_EXECUTABLE)));
+    }
+
+    // the launcher keeps its runtimes in runtime/<component>/<platform>/<component>/bin
+    let runtime_folder = Path::new(minecraft_folder).join("runtime");
+    for component in subfolders(&runtime_folder) {
+        for platform in subfolders(&component) {
+            candidates.extend(subfolders(&platform).iter().map(|runtime| runtime.join("bin").join(JAVA_EXECUTABLE)));
+        }
+    }
+
+    for folder in JAVA_INSTALL_FOLDERS {
+        let folder = match expand_env(folder) {
+            Some(folder) => folder,
+            None => continue,
+        };
+        for install in subfolders(Path::new(&folder)) {
+            candidates.push(install.join("bin").join(JAVA_EXECUTABLE));
+            candidates.push(install.join("Contents").join("Home").join("bin").join(JAVA_EXECUTABLE));
+        }
+    }
+    candidates.into_iter().filter(|candidate| candidate.is_file()).collect()
+}
+
+fn subfolders(folder: &Path) -> Vec<PathBuf> {
+    match fs::read_dir(folder) {
+        Ok(entries) => entries.flatten().map(|entry| entry.path()).filter(|path| path.is_dir()).collect(),
+        Err(_) => Vec::new(),
+    }
+}
+
+/// Same as `config::get_env_path`, but returns `None` instead of panicking when the variable is not set.
+fn expand_env(path: &str) -> Option<String> {
+    if !path.starts_with('%') {
+        return Some(path.to_string());
+    }
+    let path_splitted: Vec<&str> = path.split('%').collect();
+    env::var(path_splitted[1].to_uppercase()).ok().map(|var| var + path_splitted[2])
+}
+
+/// Runs `java -version` and returns the full version with its major version.
+pub fn java_version(java: &Path) -> Option<(String, u32)> {
+    let output = Command::new(java).arg("-version").output().ok()?;
+    // java -version prints on stderr
+    parse_java_version(&String::from_utf8_lossy(&output.stderr))
+}
+
+/// Parses the output of `java -version`, e.g. `openjdk version "17.0.8" 2023-07-18` or `java version "1.8.0_381"`.
+/// Versions before 9 are numbered `1.<major>`.
+pub fn parse_java_version(output: &str) -> Option<(String, u32)> {
+    let line = output.lines().find(|line| line.contains(" version \""))?;
+    let version = line.split('"').nth(1)?.to_string();
+    let mut numbers = version.split(|c: char| !c.is_ascii_digit()).filter(|number| !number.is_empty());
+    let major: u32 = match numbers.next()?.parse().ok()? {
+        1 => numbers.next()?.parse().ok()?,
+        major => major,
+    };
+    Some((version, major))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn install(major: u32) -> JavaInstall {
+        JavaInstall { path: PathBuf::from(format!("java{}", major)), version: major.to_string(), major }
+    }
+
+    #[test]
+    fn parse_java_version_outputs() {
+        let openjdk = "openjdk version \"17.0.8\" 2023-07-18\nOpenJDK Runtime Environment Temurin-17.0.8+7 (build 17.0.8+7)";
+        assert_eq!(parse_java_version(openjdk), Some(("17.0.8".to_string(), 17)));
+        assert_eq!(parse_java_version("java version \"1.8.0_381\"\nJava(TM) SE Runtime Environment"), Some(("1.8.0_381".to_string(), 8)));
+        assert_eq!(parse_java_version("openjdk version \"21\" 2023-09-19"), Some(("21".to_string(), 21)));
+        assert_eq!(parse_java_version("command not found"), None);
+    }
+
+    #[test]
+    fn select_java_requires_the_exact_version() {
+        let installs = [install(8), install(21)];
+        let required = JavaRequirement { min: 17, max: 17 };
+        assert!(matches!(select_java(&installs, Some(required)), JavaStatus::WrongVersion(install) if install.major == 21));
+        let installs = [install(17), install(21)];
+        assert!(matches!(select_java(&installs, Some(required)), JavaStatus::Found(install) if install.major == 17));
+    }
+
+    #[test]
+    fn select_java_in_a_range() {
+        let installs = [install(8), install(17), install(21)];
+        let required = JavaRequirement { min: 17, max: 21 };
+        assert!(matches!(select_java(&installs, Some(required)), JavaStatus::Found(install) if install.major == 21));
+        assert!(matches!(select_java(&installs, None), JavaStatus::Found(install) if install.major == 21));
+        assert!(matches!(select_java(&[], Some(required)), JavaStatus::Missing));
+    }
+}
@@ -0,0 +1,130 @@
+use std::fs::{self, File};
+use std::io::{Write, self};
+use std::path::Path;
+
+use serde_json::{json, Map, Value};
+
+use crate::config::slugify;
+
+pub const LAUNCHER_PROFILES_FILE: &str = "launcher_profiles.json";
+pub const LAUNCHER_PROFILES_BACKUP: &str = "launcher_profiles.json.bak";
+
+/// A profile of the Minecraft launcher, as written in `launcher_profiles.json`.
+#[derive(Debug)]
+pub struct LauncherProfile {
+    pub id: String,
+    pub name: String,
+    pub icon: String,
+    pub version_id: String,
+    pub game_dir: Option<String>,
+    pub memory: Option<String>,
+}
+
+impl LauncherProfile {
+    /// Id of the profile in `launcher_profiles.json`, derived from its name so updates find it again.
+    pub fn id_from_name(name: &str) -> String {
+        format!("magic-installer-{}", slugify(name))
+    }
+}
+
+/// Adds or updates `profile` in the `launcher_profiles.json` of the minecraft folder.
+/// The file is first copied in `backup_folder`, other profiles and unknown fields are left untouched.
+pub fn write_profile(minecraft_folder: &str, backup_folder: &str, profile: &LauncherProfile) -> io::Result<()> {
+    let path = Path::new(minecraft_folder).join(LAUNCHER_PROFILES_FILE);
+    let mut profiles: Value = match fs::read_to_string(&path) {
+        Ok(content) => {
+            fs::copy(&path, Path::new(backup_folder).join(LAUNCHER_PROFILES_BACKUP))?;
+            serde_json::from_str(&content)?
+        }
+        Err(err) if err.kind() == io::ErrorKind::NotFound => json!({}),
+        Err(err) => return Err(err),
+    };
+
+    let root = match profiles.as_object_mut() {
+        Some(root) => root,
+        None => return Err(io::Error::other("launcher_profiles.json is not a JSON object")),
+    };
+    let entries = root.entry("profiles").or_insert_with(|| json!({}));
+    let entries = match entries.as_object_mut() {
+        Some(entries) => entries,
+        None => return Err(io::Error::other("launcher_profiles.json has an invalid 'profiles' entry")),
+    };
+
+    let entry = entries.entry(profile.id.clone()).or_insert_with(|| json!({}));
+    if !entry.is_object() {
+        *entry = json!({});
+    }
+    let entry: &mut Map<String, Value> = entry.as_object_mut().unwrap();
+    entry.insert("name".to_string(), json!(profile.name));
+    entry.insert("type".to_string(), json!("custom"));
+    entry.insert("icon".to_string(), json!(profile.icon));
+    entry.insert("lastVersionId".to_string(), json!(profile.version_id));
+    match &profile.game_dir {
+        Some(game_dir) => {entry.insert("gameDir".to_string(), json!(game_dir));}
+        None => {entry.remove("gameDir");}
+    }
+    if let Some(memory) = &profile.memory {
+        let java_args = entry.get("javaArgs").and_then(Value::as_str).unwrap_or_default();
+        entry.insert("javaArgs".to_string(), json!(with_max_memory(java_args, memory)));
+    }
+
+    let mut file = File::create(&path)?;
+    file.write_all(serde_json::to_string_pretty(&profiles)?.as_bytes())?;
+    Ok(())
+}
+
+/// Replaces the `-Xmx` argument of `java_args` with `memory`, keeping the other arguments the player set.
+fn with_max_memory(java_args: &str, memory: &str) -> String {
+    let mut args: Vec<String> = java_args.split_whitespace()
+        .filter(|arg| !arg.starts_with("-Xmx"))
+        .map(str::to_string)
+        .collect();
+    args.insert(0, format!("-Xmx{}", memory));
+    args.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+
+    use super::*;
+
+    #[test]
+    fn write_profile_keeps_the_other_profiles() {
+        let folder = env::temp_dir().join(format!("magic_installer_launcher_profiles_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        let backup_folder = folder.join("magic_installer");
+        fs::create_dir_all(&backup_folder).unwrap();
+        let existing = json!({
+            "profiles": {
+                "vanilla": { "name": "Vanilla", "type": "latest-release", "lastVersionId": "latest-release" },
+                "magic-installer-modpack": { "name": "Modpack", "javaArgs": "-Xmx2G -XX:+UseG1GC", "resolution": { "width": 1280 } }
+            },
+            "settings": { "enableSnapshots": true },
+            "version": 3
+        });
+        fs::write(folder.join(LAUNCHER_PROFILES_FILE), existing.to_string()).unwrap();
+        let profile = LauncherProfile {
+            id: LauncherProfile::id_from_name("Modpack"),
+            name: "Modpack".to_string(),
+            icon: "Furnace".to_string(),
+            version_id: "fabric-loader-0.15.7-1.20.1".to_string(),
+            game_dir: Some("instances/modpack".to_string()),
+            memory: Some("6G".to_string()),
+        };
+
+        write_profile(&folder.to_string_lossy(), &backup_folder.to_string_lossy(), &profile).unwrap();
+        let backup: Value = serde_json::from_str(&fs::read_to_string(backup_folder.join(LAUNCHER_PROFILES_BACKUP)).unwrap()).unwrap();
+        assert_eq!(backup, existing);
+        let written: Value = serde_json::from_str(&fs::read_to_string(folder.join(LAUNCHER_PROFILES_FILE)).unwrap()).unwrap();
+        assert_eq!(written["profiles"]["vanilla"], existing["profiles"]["vanilla"]);
+        assert_eq!(written["settings"], existing["settings"]);
+        assert_eq!(written["version"], 3);
+        let entry = &written["profiles"]["magic-installer-modpack"];
+        assert_eq!(entry["javaArgs"], "-Xmx6G -XX:+UseG1GC");
+        assert_eq!(entry["resolution"]["width"], 1280);
+        assert_eq!(entry["lastVersionId"], "fabric-loader-0.15.7-1.20.1");
+        assert_eq!(entry["gameDir"], "instances/modpack");
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
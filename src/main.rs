@@ -1,35 +1,135 @@
 mod config;
 mod app;
 mod files;
+mod modloader;
+mod launcher;
+mod java;
+mod manifest;
+mod record;
+mod options;
+mod mrpack;
+mod curseforge;
+mod packwiz;
+mod scheduler;
+mod cache;
+mod http;
+mod rate_limit;
+mod update;
+mod signature;
+mod extract;
+mod plan;
 
 use crate::config::Config;
 use crate::app::{Display, AppStatus};
-use crate::files::create_folder;
+use crate::files::{create_folder, format_size};
+use crate::cache::Cache;
+use crate::plan::plan_install;
+use crate::record::InstallRecord;
+use crate::rate_limit::{parse_rate, set_rate_limit};
 use std::env;
+use std::io::{self, IsTerminal, Write};
+use std::path::PathBuf;
 
 fn main() -> crossterm::Result<()> {
     let config_str = include_str!("../config.txt");
     let mut debug: bool = false;
     
     let args: Vec<String> = env::args().collect();
-    if args.len() > 1 {
-        match args[1].as_str() {
-            "debug" => {debug = true;}
-            _ => {}
-        }
+    if args.len() > 1 && args[1] == "debug" {
+        debug = true;
     }
 
-    let config: Config = Config::from(config_str, debug);
+    let mut config: Config = Config::from(config_str, debug);
     create_folder(&config.magic_installer_folder);
+    http::init(&config.http)?;
+
+    // --limit-rate <rate> overrides max_download_rate, e.g. 500K or 2M
+    if let Some(rate) = args.iter().position(|arg| arg == "--limit-rate").and_then(|index| args.get(index + 1)) {
+        config.max_download_rate = parse_rate(rate).unwrap_or_else(|| panic!("Invalid --limit-rate '{}'", rate));
+    }
+    set_rate_limit(config.max_download_rate);
+    if config.debug {
+        let config_dump = format!("{:#?}", config);
+        config.log(&config_dump);
+    }
+
+    // the binary replaced by the last update can only be deleted once it stopped running
+    if let Err(error) = update::remove_old_executable() {
+        config.log(format!("Couldn't remove the old installer: {}", error).as_str());
+    }
+    let executable = env::current_exe()?;
+    // --yes answers Oui to every confirmation, for scripted runs
+    let assume_yes = args.iter().any(|arg| arg == "--yes");
 
-    let mut display = Display::open(config)?;
-    loop {
-        if let AppStatus::Exit = display.main_menu()? {break;}
-        crossterm::event::read().unwrap();
+    if args.iter().any(|arg| arg == "--clear-cache") {
+        let mut cache = Cache::open(&config.magic_installer_folder, config.cache_max_size);
+        if !confirm(&format!("Vider le cache ({}) ?", format_size(cache.size())), assume_yes)? { //lang
+            return Ok(());
+        }
+        let freed = cache.clear()?;
+        println!("Cache vidé : {} libérés", format_size(freed)); //lang
+        return Ok(());
+    }
+
+    // --from <archive or folder> installs a local modpack instead of downloading it
+    let from: Option<PathBuf> = args.iter().position(|arg| arg == "--from")
+        .and_then(|index| args.get(index + 1))
+        .map(|path| env::current_dir().map(|folder| folder.join(path)).unwrap_or(PathBuf::from(path)));
+    if let Some(from) = &from {
+        config.log(format!("install from: {}", from.display()).as_str());
+    }
+
+    // --dry-run prints what installing each pack would do, without changing anything
+    if args.iter().any(|arg| arg == "--dry-run") {
+        for pack in &config.packs {
+            let mut pack = pack.clone();
+            if let Some(record) = InstallRecord::load(&config.magic_installer_folder, &pack.id) {
+                pack.apply_record(&record);
+            }
+            match plan_install(&config, &pack, from.as_deref()) {
+                Ok(plan) => plan.lines().iter().for_each(|(_, line)| println!("{}", line)),
+                Err(error) => println!("{} : {}", pack.name, error),
+            }
+            println!();
+        }
+        return Ok(());
+    }
+
+    let mut display = Display::open(config, from, assume_yes)?;
+    'packs: loop {
+        if let AppStatus::Exit = display.pack_menu()? {break;}
+        loop {
+            match display.main_menu()? {
+                AppStatus::Exit => break 'packs,
+                AppStatus::Back => break,
+                AppStatus::Restart => {
+                    display.close()?;
+                    return update::relaunch(&executable, &args);
+                }
+                AppStatus::Loop => {crossterm::event::read().unwrap();}
+            }
+        }
     }
     display.close()?;
     Ok(())
 }
 
+/// Asks `question` on the console before a destructive action, `--yes` answering for the player.
+/// Without a terminal to answer, the action is refused.
+fn confirm(question: &str, assume_yes: bool) -> io::Result<bool> {
+    if assume_yes {
+        return Ok(true);
+    }
+    if !io::stdin().is_terminal() {
+        println!("{} Relancez avec --yes pour confirmer", question); //lang
+        return Ok(false);
+    }
+    print!("{} [o/N] ", question);
+    io::stdout().flush()?;
+    let mut answer = String::new();
+    io::stdin().read_line(&mut answer)?;
+    Ok(matches!(answer.trim(), "o" | "O" | "oui" | "Oui"))
+}
+
 //TODO 
 // - Add a way to change config.
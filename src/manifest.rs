@@ -0,0 +1,306 @@
+use std::collections::HashMap;
+use std::io;
+use std::path::{Component, Path, PathBuf};
+
+use serde::Deserialize;
+
+use crate::files::{has_supported_hash, is_unchanged};
+use crate::signature::{fetch_signed_string, signature_error};
+use crate::modloader::ModloaderKind;
+use crate::record::InstallRecord;
+
+/// A modpack described file by file, as an alternative to a single zip.
+///
+/// ```json
+/// {
+///     "files": [
+///         { "path": "mods/sodium.jar", "url": "https://...", "hashes": { "sha256": "..." } },
+///         { "category": "mod", "path": "iris.jar", "url": "https://...", "optional": true, "default": false,
+///           "name": "Iris", "description": "Support des shaders" },
+///         { "category": "resourcepack", "path": "Faithful.zip", "url": "https://...", "enable": true },
+///         { "category": "options", "path": "options.txt", "url": "https://...", "policy": "merge-keys" }
+///     ]
+/// }
+/// ```
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct Manifest {
+    pub files: Vec<ManifestFile>,
+    /// Folders copied over the instance folder once the files are installed, for imported modpacks.
+    #[serde(skip)]
+    pub overrides: Vec<PathBuf>,
+    /// Minecraft version required by an imported modpack.
+    #[serde(skip)]
+    pub minecraft_version: Option<String>,
+    /// Modloader and its version required by an imported modpack.
+    #[serde(skip)]
+    pub modloader: Option<(ModloaderKind, String)>,
+}
+
+/// Kind of content of a manifest file, deciding the folder it goes in.
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub enum Category {
+    #[serde(rename = "mod")]
+    Mod,
+    #[serde(rename = "config")]
+    Config,
+    #[serde(rename = "resourcepack")]
+    ResourcePack,
+    #[serde(rename = "shaderpack")]
+    ShaderPack,
+    /// Loaded in every world by mods such as Global Packs or Paxi.
+    #[serde(rename = "datapack")]
+    DataPack,
+    /// `options.txt` and the other files at the root of the instance.
+    #[serde(rename = "options")]
+    Options,
+}
+
+impl Category {
+    /// Folder of the category in the instance folder.
+    pub fn folder(&self) -> &'static str {
+        match self {
+            Category::Mod => "mods",
+            Category::Config => "config",
+            Category::ResourcePack => "resourcepacks",
+            Category::ShaderPack => "shaderpacks",
+            Category::DataPack => "datapacks",
+            Category::Options => "",
+        }
+    }
+}
+
+/// What to do when a manifest file already exists in the instance folder.
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Policy {
+    /// The file is always replaced.
+    #[default]
+    Overwrite,
+    /// The file is only installed if the player doesn't have it.
+    IfMissing,
+    /// For `key:value` files like `options.txt`: the keys the player doesn't have are added, the others are kept.
+    MergeKeys,
+    /// Like `if-missing`, but the file then belongs to the player and is never removed by updates or uninstalls.
+    Preserve,
+}
+
+/// What installing a manifest file does, depending on the file already at its target.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum FileAction {
+    /// The player's file is kept, for `if-missing` and `preserve`.
+    Keep,
+    /// The file already matches the manifest hashes.
+    UpToDate,
+    /// The file is downloaded next to the player's one, then its missing keys are merged into it.
+    Merge,
+    /// The file is downloaded, replacing the existing one if any.
+    Download,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ManifestFile {
+    /// Path of the file in the folder of its category, or in the instance folder without category.
+    pub path: String,
+    pub category: Option<Category>,
+    pub url: String,
+    /// Urls serving the same file, tried in order when `url` fails.
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// Expected hex digests of the file, keyed by algorithm (`sha1`, `sha256` or `sha512`).
+    #[serde(default)]
+    pub hashes: HashMap<String, String>,
+    /// Size in bytes, only used to preview the install.
+    pub size: Option<u64>,
+    #[serde(default)]
+    pub optional: bool,
+    /// Whether an optional file is selected the first time the pack is installed.
+    #[serde(default)]
+    pub default: bool,
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: String,
+    /// For resource packs, adds the pack to the enabled ones in `options.txt`.
+    #[serde(default)]
+    pub enable: bool,
+    #[serde(default)]
+    pub policy: Policy,
+}
+
+impl Manifest {
+    /// Fetches the manifest at `url`, checked against one of `signatures` when they are given.
+    pub fn fetch(url: &str, signatures: Option<&[String]>) -> io::Result<Manifest> {
+        let body = fetch_signed_string(url, signatures)?;
+        serde_json::from_str(&body).map_err(io::Error::other)
+    }
+
+    /// Paths of the optional files selected before the checklist is shown: the selection of the `previous` install
+    /// for the files it offered, the manifest defaults for the others.
+    pub fn initial_selection(&self, previous: Option<&InstallRecord>) -> Vec<String> {
+        self.optional_files().into_iter()
+            .filter(|file| match previous {
+                Some(previous) if previous.has_offered(&file.relative_path()) => previous.optional.contains(&file.relative_path()),
+                _ => file.default,
+            })
+            .map(|file| file.relative_path())
+            .collect()
+    }
+
+    /// With signatures required, a signed manifest only covers its `files` through their hashes,
+    /// so a file without a hash the installer can check is refused.
+    pub fn check_hashes(files: &[&ManifestFile]) -> io::Result<()> {
+        match files.iter().find(|file| !has_supported_hash(&file.hashes)) {
+            Some(file) => Err(signature_error(format!("{} n'a pas d'empreinte vérifiable", file.relative_path()))), //lang
+            None => Ok(()),
+        }
+    }
+
+    pub fn optional_files(&self) -> Vec<&ManifestFile> {
+        self.files.iter().filter(|file| file.optional).collect()
+    }
+
+    /// The files to install: every required file, and the optional ones in `selected`.
+    pub fn selected_files(&self, selected: &[String]) -> Vec<&ManifestFile> {
+        self.files.iter().filter(|file| !file.optional || selected.contains(&file.relative_path())).collect()
+    }
+}
+
+impl ManifestFile {
+    /// Path of the file relative to the instance folder, also identifies the file in the install record.
+    pub fn relative_path(&self) -> String {
+        match self.category.map(|category| category.folder()) {
+            Some(folder) if !folder.is_empty() => format!("{}/{}", folder, self.path),
+            _ => self.path.clone(),
+        }
+    }
+
+    /// Where the file goes in the instance folder, refusing paths that would escape it.
+    pub fn target(&self, instance_folder: &str) -> io::Result<PathBuf> {
+        let relative_path = self.relative_path();
+        let path = Path::new(&relative_path);
+        if !path.components().all(|component| matches!(component, Component::Normal(_))) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("Invalid path in manifest: {}", self.path)));
+        }
+        Ok(Path::new(instance_folder).join(path))
+    }
+
+    /// What installing the file does given its `target`, see `ManifestFile::target`.
+    pub fn action(&self, target: &Path) -> FileAction {
+        match (self.policy, target.exists()) {
+            (Policy::IfMissing | Policy::Preserve, true) => FileAction::Keep,
+            (Policy::Overwrite, true) if is_unchanged(target, &self.hashes) => FileAction::UpToDate,
+            (Policy::MergeKeys, true) => FileAction::Merge,
+            _ => FileAction::Download,
+        }
+    }
+
+    /// Whether the file goes in the install record once installed with `action`, so updates and removals delete it.
+    /// Merged and preserved files belong to the player, and a kept `if-missing` file only belongs to the modpack
+    /// if the `previous` install created it.
+    pub fn is_recorded(&self, action: FileAction, previous: Option<&InstallRecord>) -> bool {
+        match (self.policy, action) {
+            (Policy::MergeKeys | Policy::Preserve, _) => false,
+            (_, FileAction::Keep) => previous.is_some_and(|record| record.files.contains(&self.relative_path())),
+            _ => true,
+        }
+    }
+
+    /// Name the resource pack has in `options.txt`, `None` if the file is not a resource pack to enable.
+    pub fn enabled_resource_pack(&self) -> Option<String> {
+        if !self.enable || self.category != Some(Category::ResourcePack) {
+            return None;
+        }
+        Path::new(&self.path).file_name().map(|name| format!("file/{}", name.to_string_lossy()))
+    }
+
+    /// `url` followed by its mirrors.
+    pub fn urls(&self) -> Vec<String> {
+        let mut urls = vec![self.url.clone()];
+        urls.extend(self.mirrors.iter().cloned());
+        urls
+    }
+
+    /// Name shown to the player, defaults to the file name.
+    pub fn display_name(&self) -> String {
+        match &self.name {
+            Some(name) => name.clone(),
+            None => Path::new(&self.path).file_name().map_or(self.path.clone(), |name| name.to_string_lossy().to_string()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MANIFEST: &str = r#"{
+        "files": [
+            { "path": "mods/sodium.jar", "url": "https://example.com/sodium.jar", "hashes": { "sha256": "aa" } },
+            { "category": "mod", "path": "iris.jar", "url": "https://example.com/iris.jar", "optional": true, "default": true },
+            { "category": "mod", "path": "zoom.jar", "url": "https://example.com/zoom.jar", "optional": true },
+            { "category": "options", "path": "options.txt", "url": "https://example.com/options.txt", "policy": "merge-keys" },
+            { "category": "config", "path": "keys.json", "url": "https://example.com/keys.json", "policy": "if-missing" }
+        ]
+    }"#;
+
+    fn manifest() -> Manifest {
+        serde_json::from_str(MANIFEST).unwrap()
+    }
+
+    #[test]
+    fn initial_selection_uses_defaults_first() {
+        assert_eq!(manifest().initial_selection(None), vec!["mods/iris.jar"]);
+    }
+
+    #[test]
+    fn initial_selection_keeps_the_previous_choices() {
+        let record = InstallRecord {
+            optional: vec!["mods/zoom.jar".to_string()],
+            offered: Some(vec!["mods/iris.jar".to_string(), "mods/zoom.jar".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(manifest().initial_selection(Some(&record)), vec!["mods/zoom.jar"]);
+    }
+
+    #[test]
+    fn initial_selection_applies_defaults_to_new_files() {
+        // iris was added to the manifest after this install
+        let record = InstallRecord {
+            optional: vec!["mods/zoom.jar".to_string()],
+            offered: Some(vec!["mods/zoom.jar".to_string()]),
+            ..Default::default()
+        };
+        assert_eq!(manifest().initial_selection(Some(&record)), vec!["mods/iris.jar", "mods/zoom.jar"]);
+        // older records only know the selected files
+        let record = InstallRecord { optional: vec!["mods/zoom.jar".to_string()], ..Default::default() };
+        assert_eq!(manifest().initial_selection(Some(&record)), vec!["mods/zoom.jar"]);
+    }
+
+    #[test]
+    fn only_files_of_the_modpack_are_recorded() {
+        let manifest = manifest();
+        let (sodium, options, keys) = (&manifest.files[0], &manifest.files[3], &manifest.files[4]);
+        assert!(sodium.is_recorded(FileAction::Download, None));
+        assert!(sodium.is_recorded(FileAction::UpToDate, None));
+        assert!(!options.is_recorded(FileAction::Merge, None));
+        assert!(!options.is_recorded(FileAction::Download, None));
+        assert!(keys.is_recorded(FileAction::Download, None));
+        assert!(!keys.is_recorded(FileAction::Keep, None));
+        let record = InstallRecord { files: vec!["config/keys.json".to_string()], ..Default::default() };
+        assert!(keys.is_recorded(FileAction::Keep, Some(&record)));
+    }
+
+    #[test]
+    fn check_hashes_refuses_unverifiable_files() {
+        let manifest = manifest();
+        assert!(Manifest::check_hashes(&[&manifest.files[0]]).is_ok());
+        let error = Manifest::check_hashes(&manifest.files.iter().collect::<Vec<_>>()).unwrap_err();
+        assert!(crate::signature::is_signature_error(&error));
+    }
+
+    #[test]
+    fn target_refuses_paths_leaving_the_instance() {
+        let file = ManifestFile { path: "../../evil.jar".to_string(), category: Some(Category::Mod), ..Default::default() };
+        assert!(file.target("instance").is_err());
+        assert_eq!(manifest().files[1].target("instance").unwrap(), Path::new("instance").join("mods/iris.jar"));
+    }
+}
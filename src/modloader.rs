@@ -0,0 +1,219 @@
+use std::collections::HashMap;
+use std::fs::{File, create_dir_all};
+use std::io::{Write, self};
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::Pack;
+use crate::files::fetch_string;
+
+pub const FABRIC_META_URL: &str = "https://meta.fabricmc.net";
+pub const QUILT_META_URL: &str = "https://meta.quiltmc.org";
+pub const FORGE_MAVEN_URL: &str = "https://maven.minecraftforge.net";
+pub const NEOFORGE_MAVEN_URL: &str = "https://maven.neoforged.net/releases";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ModloaderKind {
+    Fabric,
+    Quilt,
+    Forge,
+    NeoForge,
+}
+
+impl ModloaderKind {
+    pub fn from(kind: &str) -> Option<ModloaderKind> {
+        match kind.to_lowercase().as_str() {
+            "fabric" => Some(ModloaderKind::Fabric),
+            "quilt" => Some(ModloaderKind::Quilt),
+            "forge" => Some(ModloaderKind::Forge),
+            "neoforge" => Some(ModloaderKind::NeoForge),
+            _ => None,
+        }
+    }
+
+    /// Name of the modloader as shown in the menus.
+    pub fn label(&self) -> &'static str {
+        match self {
+            ModloaderKind::Fabric => "Fabric",
+            ModloaderKind::Quilt => "Quilt",
+            ModloaderKind::Forge => "Forge",
+            ModloaderKind::NeoForge => "NeoForge",
+        }
+    }
+
+    /// Id of the folder the modloader creates in `.minecraft/versions`.
+    pub fn version_id(&self, minecraft_version: &str, loader_version: &str) -> String {
+        match self {
+            ModloaderKind::Fabric => format!("fabric-loader-{}-{}", loader_version, minecraft_version),
+            ModloaderKind::Quilt => format!("quilt-loader-{}-{}", loader_version, minecraft_version),
+            ModloaderKind::Forge => format!("{}-forge-{}", minecraft_version, loader_version),
+            ModloaderKind::NeoForge => format!("neoforge-{}", loader_version),
+        }
+    }
+}
+
+/// How the modloader gets installed, chosen from the config by `install_strategy`.
+#[derive(Debug)]
+pub enum InstallStrategy {
+    /// The loader profile is fetched from a meta API and written in `versions` (Fabric, Quilt).
+    Profile { meta_url: String, minecraft_version: String, loader_version: String },
+    /// An installer is downloaded from the first working url then run headlessly, `archive` is set when it comes zipped.
+    Installer { urls: Vec<String>, hashes: HashMap<String, String>, filename: String, archive: bool, args: Vec<String> },
+}
+
+/// Picks the install strategy of the modloader of `pack`.
+/// A configured `modloader_url` always wins, otherwise the strategy depends on `modloader.kind`.
+pub fn install_strategy(pack: &Pack, minecraft_folder: &str) -> Option<InstallStrategy> {
+    if let Some(url) = &pack.modloader_url {
+        let mut urls = vec![url.clone()];
+        urls.extend(pack.modloader_mirrors.iter().cloned());
+        return Some(match &pack.modloader_execname {
+            Some(execname) => InstallStrategy::Installer {
+                urls,
+                hashes: pack.modloader_hashes.clone(),
+                filename: execname.clone(),
+                archive: true,
+                args: pack.modloader_args.clone(),
+            },
+            None => InstallStrategy::Installer {
+                urls,
+                hashes: pack.modloader_hashes.clone(),
+                filename: installer_filename(pack.modloader_kind),
+                archive: false,
+                args: pack.modloader_args.clone(),
+            },
+        });
+    }
+
+    let minecraft_version = pack.minecraft_version.clone()?;
+    let loader_version = pack.modloader_version.clone()?;
+    match pack.modloader_kind {
+        ModloaderKind::Fabric | ModloaderKind::Quilt => Some(InstallStrategy::Profile {
+            meta_url: pack.modloader_meta_url.clone().unwrap_or(match pack.modloader_kind {
+                ModloaderKind::Quilt => QUILT_META_URL.to_string(),
+                _ => FABRIC_META_URL.to_string(),
+            }),
+            minecraft_version,
+            loader_version,
+        }),
+        ModloaderKind::Forge | ModloaderKind::NeoForge => {
+            let url = match pack.modloader_kind {
+                ModloaderKind::Forge => format!("{}/net/minecraftforge/forge/{mc}-{v}/forge-{mc}-{v}-installer.jar",
+                    FORGE_MAVEN_URL, mc = minecraft_version, v = loader_version),
+                _ => format!("{}/net/neoforged/neoforge/{v}/neoforge-{v}-installer.jar",
+                    NEOFORGE_MAVEN_URL, v = loader_version),
+            };
+            let args = if pack.modloader_args.is_empty() {
+                vec!["--installClient".to_string(), minecraft_folder.to_string()]
+            } else {
+                pack.modloader_args.clone()
+            };
+            Some(InstallStrategy::Installer {
+                urls: vec![url],
+                hashes: pack.modloader_hashes.clone(),
+                filename: installer_filename(pack.modloader_kind),
+                archive: false,
+                args,
+            })
+        }
+    }
+}
+
+/// The version id the configured modloader should create, `modloader.version_id` overrides the computed one.
+pub fn expected_version_id(pack: &Pack) -> Option<String> {
+    if let Some(version_id) = &pack.modloader_version_id {
+        return Some(version_id.clone());
+    }
+    match (&pack.minecraft_version, &pack.modloader_version) {
+        (Some(minecraft_version), Some(loader_version)) => Some(pack.modloader_kind.version_id(minecraft_version, loader_version)),
+        _ => None,
+    }
+}
+
+fn installer_filename(kind: ModloaderKind) -> String {
+    format!("{}-installer.jar", kind.label().to_lowercase())
+}
+
+/// Fetches the launcher profile for a Minecraft + loader version from the meta API at `meta_url`.
+/// `meta_url` is configurable so a local stand-in serving fixture JSON can be used instead of the official one.
+pub fn fetch_loader_profile(kind: ModloaderKind, meta_url: &str, minecraft_version: &str, loader_version: &str) -> io::Result<Value> {
+    let api_version = match kind {
+        ModloaderKind::Quilt => "v3",
+        _ => "v2",
+    };
+    let url = format!("{}/{}/versions/loader/{}/{}/profile/json",
+        meta_url.trim_end_matches('/'), api_version, minecraft_version, loader_version);
+    let body = fetch_string(&url)?;
+    serde_json::from_str(&body).map_err(io::Error::other)
+}
+
+/// Writes a version profile into `versions/<id>/<id>.json` of the minecraft folder and returns its id.
+/// An empty `<id>.jar` is created next to it, like the official Fabric installer does.
+pub fn install_version_profile(minecraft_folder: &str, profile: &Value) -> io::Result<String> {
+    let id = match profile.get("id").and_then(Value::as_str) {
+        Some(id) => id.to_string(),
+        None => return Err(io::Error::other("Version profile has no id")),
+    };
+
+    let version_folder = Path::new(minecraft_folder).join("versions").join(&id);
+    create_dir_all(&version_folder)?;
+
+    let mut file = File::create(version_folder.join(format!("{}.json", id)))?;
+    file.write_all(serde_json::to_string_pretty(profile)?.as_bytes())?;
+    File::create(version_folder.join(format!("{}.jar", id)))?;
+    Ok(id)
+}
+
+/// Checks that `versions/<id>/<id>.json` exists in the minecraft folder.
+pub fn is_version_installed(minecraft_folder: &str, version_id: &str) -> bool {
+    Path::new(minecraft_folder).join("versions").join(version_id).join(format!("{}.json", version_id)).exists()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+
+    use super::*;
+    use crate::http::tests::serve;
+
+    #[test]
+    fn profiles_are_installed_from_the_meta_api() {
+        let folder = env::temp_dir().join(format!("magic_installer_modloader_meta_{}", std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        let minecraft_folder = folder.display().to_string();
+        let fabric = r#"{"id":"fabric-loader-0.15.7-1.20.1","inheritsFrom":"1.20.1","mainClass":"net.fabricmc.loader.impl.launch.knot.KnotClient"}"#;
+        let quilt = r#"{"id":"quilt-loader-0.23.1-1.20.1","inheritsFrom":"1.20.1","mainClass":"org.quiltmc.loader.impl.launch.knot.KnotClient"}"#;
+        let meta_url = serve(vec![
+            ("/v2/versions/loader/1.20.1/0.15.7/profile/json", fabric.as_bytes().to_vec()),
+            ("/v3/versions/loader/1.20.1/0.23.1/profile/json", quilt.as_bytes().to_vec()),
+        ]);
+
+        for (kind, loader_version, fixture) in [("fabric", "0.15.7", fabric), ("quilt", "0.23.1", quilt)] {
+            let config: HashMap<String, String> = [
+                ("modpack_url", "https://example.com/pack.zip"),
+                ("minecraft_version", "1.20.1"),
+                ("modloader.kind", kind),
+                ("modloader.version", loader_version),
+                ("modloader.meta_url", &meta_url),
+            ].iter().map(|(key, value)| (key.to_string(), value.to_string())).collect();
+            let pack = Pack::from(None, &config, &minecraft_folder);
+            let Some(InstallStrategy::Profile { meta_url, minecraft_version, loader_version }) = install_strategy(&pack, &minecraft_folder) else {
+                panic!("{} should be installed from its meta API", kind);
+            };
+
+            let profile = fetch_loader_profile(pack.modloader_kind, &meta_url, &minecraft_version, &loader_version).unwrap();
+            let id = install_version_profile(&minecraft_folder, &profile).unwrap();
+            assert_eq!(Some(id.clone()), expected_version_id(&pack));
+            assert!(is_version_installed(&minecraft_folder, &id));
+            let version_folder = folder.join("versions").join(&id);
+            let written: Value = serde_json::from_str(&fs::read_to_string(version_folder.join(format!("{}.json", id))).unwrap()).unwrap();
+            assert_eq!(written, serde_json::from_str::<Value>(fixture).unwrap());
+            assert_eq!(fs::metadata(version_folder.join(format!("{}.jar", id))).unwrap().len(), 0);
+        }
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
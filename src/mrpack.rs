@@ -0,0 +1,141 @@
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::manifest::{Manifest, ManifestFile};
+use crate::modloader::ModloaderKind;
+
+pub const MRPACK_FILE: &str = "pack.mrpack";
+pub const INDEX_FILE: &str = "modrinth.index.json";
+/// Folders of the archive copied over the instance folder, `client-overrides` after `overrides` so it wins.
+pub const OVERRIDES_FOLDERS: &[&str] = &["overrides", "client-overrides"];
+
+/// `modrinth.index.json`, at the root of a Modrinth `.mrpack` archive.
+#[derive(Debug, Deserialize)]
+struct Index {
+    files: Vec<IndexFile>,
+    /// Versions of `minecraft` and of the modloader, e.g. `"fabric-loader": "0.14.21"`.
+    #[serde(default)]
+    dependencies: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct IndexFile {
+    path: String,
+    #[serde(default)]
+    hashes: HashMap<String, String>,
+    #[serde(rename = "fileSize")]
+    file_size: Option<u64>,
+    env: Option<Env>,
+    downloads: Vec<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct Env {
+    client: String,
+}
+
+/// Reads the index of a `.mrpack` extracted in `folder` into a manifest, with its overrides and modloader.
+/// Files unsupported on the client are left out, optional ones are offered in the optional files checklist.
+pub fn read_mrpack(folder: &Path) -> io::Result<Manifest> {
+    parse_mrpack(&fs::read_to_string(folder.join(INDEX_FILE))?, folder)
+}
+
+/// Reads the content of `modrinth.index.json`, the overrides being in `folder`.
+pub fn parse_mrpack(content: &str, folder: &Path) -> io::Result<Manifest> {
+    let index: Index = serde_json::from_str(content).map_err(io::Error::other)?;
+
+    let files: Vec<ManifestFile> = index.files.into_iter()
+        .filter(|file| file.env.as_ref().is_none_or(|env| env.client != "unsupported"))
+        .map(|file| {
+            let optional = file.env.as_ref().is_some_and(|env| env.client == "optional");
+            ManifestFile {
+                url: file.downloads.first().cloned().unwrap_or_default(),
+                mirrors: file.downloads.iter().skip(1).cloned().collect(),
+                path: file.path,
+                hashes: file.hashes,
+                size: file.file_size,
+                optional,
+                default: optional,
+                ..Default::default()
+            }
+        })
+        .collect();
+    if let Some(file) = files.iter().find(|file| file.url.is_empty()) {
+        return Err(io::Error::new(io::ErrorKind::InvalidData, format!("No download for {}", file.path)));
+    }
+
+    let modloader = index.dependencies.iter().find_map(|(name, version)| {
+        let kind = match name.as_str() {
+            "fabric-loader" => ModloaderKind::Fabric,
+            "quilt-loader" => ModloaderKind::Quilt,
+            "forge" => ModloaderKind::Forge,
+            "neoforge" => ModloaderKind::NeoForge,
+            _ => return None,
+        };
+        Some((kind, version.clone()))
+    });
+
+    Ok(Manifest {
+        files,
+        overrides: OVERRIDES_FOLDERS.iter().map(|overrides| folder.join(overrides)).collect(),
+        minecraft_version: index.dependencies.get("minecraft").cloned(),
+        modloader,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INDEX: &str = r#"{
+        "formatVersion": 1,
+        "game": "minecraft",
+        "versionId": "1.0.0",
+        "name": "Test pack",
+        "files": [
+            {"path": "mods/sodium.jar", "hashes": {"sha1": "aa", "sha512": "bb"}, "fileSize": 1024,
+             "downloads": ["https://cdn.modrinth.com/sodium.jar", "https://mirror.example.com/sodium.jar"]},
+            {"path": "mods/server-only.jar", "env": {"client": "unsupported", "server": "required"},
+             "downloads": ["https://cdn.modrinth.com/server-only.jar"]},
+            {"path": "resourcepacks/faithful.zip", "env": {"client": "optional", "server": "unsupported"},
+             "downloads": ["https://cdn.modrinth.com/faithful.zip"]}
+        ],
+        "dependencies": {"minecraft": "1.20.1", "fabric-loader": "0.14.21"}
+    }"#;
+
+    #[test]
+    fn parse_mrpack_files() {
+        let manifest = parse_mrpack(INDEX, Path::new("imports")).unwrap();
+        let paths: Vec<&str> = manifest.files.iter().map(|file| file.path.as_str()).collect();
+        assert_eq!(paths, vec!["mods/sodium.jar", "resourcepacks/faithful.zip"]);
+
+        let sodium = &manifest.files[0];
+        assert_eq!(sodium.url, "https://cdn.modrinth.com/sodium.jar");
+        assert_eq!(sodium.mirrors, vec!["https://mirror.example.com/sodium.jar"]);
+        assert_eq!(sodium.hashes.get("sha512").map(String::as_str), Some("bb"));
+        assert_eq!(sodium.size, Some(1024));
+        assert!(!sodium.optional);
+
+        let faithful = &manifest.files[1];
+        assert!(faithful.optional && faithful.default);
+    }
+
+    #[test]
+    fn parse_mrpack_versions_and_overrides() {
+        let manifest = parse_mrpack(INDEX, Path::new("imports")).unwrap();
+        assert_eq!(manifest.minecraft_version.as_deref(), Some("1.20.1"));
+        assert!(matches!(manifest.modloader, Some((ModloaderKind::Fabric, ref version)) if version == "0.14.21"));
+        assert_eq!(manifest.overrides, vec![Path::new("imports").join("overrides"), Path::new("imports").join("client-overrides")]);
+    }
+
+    #[test]
+    fn parse_mrpack_rejects_files_without_download() {
+        let index = r#"{"files": [{"path": "mods/a.jar", "downloads": []}]}"#;
+        assert!(parse_mrpack(index, Path::new("imports")).is_err());
+        assert!(parse_mrpack("not json", Path::new("imports")).is_err());
+    }
+}
@@ -0,0 +1,100 @@
+use std::fs::{self, File};
+use std::io::{Write, self};
+use std::path::Path;
+
+pub const OPTIONS_FILE: &str = "options.txt";
+const RESOURCE_PACKS_KEY: &str = "resourcePacks";
+
+/// Adds `resource_packs` (as named in `options.txt`, e.g. `file/Faithful.zip`) to the enabled resource packs of the instance.
+/// Packs the player already enabled are kept, `options.txt` is created if the game never ran.
+pub fn enable_resource_packs(instance_folder: &str, resource_packs: &[String]) -> io::Result<()> {
+    let path = Path::new(instance_folder).join(OPTIONS_FILE);
+    let content = match fs::read_to_string(&path) {
+        Ok(content) => content,
+        Err(err) if err.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(err) => return Err(err),
+    };
+
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let index = lines.iter().position(|line| line.split_once(':').is_some_and(|(key, _)| key == RESOURCE_PACKS_KEY));
+    let mut enabled: Vec<String> = match index {
+        Some(index) => serde_json::from_str(lines[index].split_once(':').unwrap().1).unwrap_or_default(),
+        None => vec!["vanilla".to_string()],
+    };
+    resource_packs.iter().for_each(|pack| {
+        if !enabled.contains(pack) {
+            enabled.push(pack.clone());
+        }
+    });
+
+    let line = format!("{}:{}", RESOURCE_PACKS_KEY, serde_json::to_string(&enabled)?);
+    match index {
+        Some(index) => lines[index] = line,
+        None => lines.push(line),
+    }
+
+    let mut file = File::create(&path)?;
+    writeln!(file, "{}", lines.join("\n"))?;
+    Ok(())
+}
+
+/// Adds to the `key:value` file `target` the keys of `defaults` it doesn't have, the values already in `target` are kept.
+pub fn merge_keys(target: &Path, defaults: &Path) -> io::Result<()> {
+    let content = fs::read_to_string(target)?;
+    let keys: Vec<&str> = content.lines().filter_map(|line| line.split_once(':').map(|(key, _)| key)).collect();
+    let defaults = fs::read_to_string(defaults)?;
+    let missing: Vec<&str> = defaults.lines()
+        .filter(|line| line.split_once(':').is_some_and(|(key, _)| !keys.contains(&key)))
+        .collect();
+    if missing.is_empty() {
+        return Ok(());
+    }
+
+    let mut file = File::create(target)?;
+    let content = content.trim_end_matches('\n');
+    writeln!(file, "{}\n{}", content, missing.join("\n"))?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::path::PathBuf;
+
+    use super::*;
+
+    fn test_folder(name: &str) -> PathBuf {
+        let folder = env::temp_dir().join(format!("magic_installer_options_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        folder
+    }
+
+    #[test]
+    fn merge_keys_keeps_the_player_values() {
+        let folder = test_folder("merge");
+        let (target, defaults) = (folder.join(OPTIONS_FILE), folder.join("options.txt.defaults"));
+        fs::write(&target, "fov:0.5\nkey_key.jump:key.keyboard.space\n").unwrap();
+        fs::write(&defaults, "fov:0.0\nrenderDistance:8\nguiScale:2\n").unwrap();
+
+        merge_keys(&target, &defaults).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "fov:0.5\nkey_key.jump:key.keyboard.space\nrenderDistance:8\nguiScale:2\n");
+        // nothing is missing anymore, the file is left as it is
+        merge_keys(&target, &defaults).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "fov:0.5\nkey_key.jump:key.keyboard.space\nrenderDistance:8\nguiScale:2\n");
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn enable_resource_packs_keeps_enabled_packs() {
+        let folder = test_folder("packs");
+        let instance = format!("{}/", folder.display());
+        enable_resource_packs(&instance, &["file/Faithful.zip".to_string()]).unwrap();
+        assert_eq!(fs::read_to_string(folder.join(OPTIONS_FILE)).unwrap(), "resourcePacks:[\"vanilla\",\"file/Faithful.zip\"]\n");
+
+        fs::write(folder.join(OPTIONS_FILE), "fov:0.5\nresourcePacks:[\"vanilla\",\"file/Mine.zip\"]\n").unwrap();
+        enable_resource_packs(&instance, &["file/Faithful.zip".to_string(), "file/Mine.zip".to_string()]).unwrap();
+        assert_eq!(fs::read_to_string(folder.join(OPTIONS_FILE)).unwrap(), "fov:0.5\nresourcePacks:[\"vanilla\",\"file/Mine.zip\",\"file/Faithful.zip\"]\n");
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
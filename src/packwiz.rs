@@ -0,0 +1,166 @@
+use std::collections::HashMap;
+use std::io;
+
+use serde::Deserialize;
+
+use crate::files::{bytes_hash, fetch_string};
+use crate::manifest::{Manifest, ManifestFile, Policy};
+use crate::modloader::ModloaderKind;
+use crate::signature::{fetch_signed_string, signature_error};
+
+/// `pack.toml`, the entry point of a packwiz modpack.
+#[derive(Debug, Deserialize)]
+struct PackToml {
+    index: IndexRef,
+    /// Versions of `minecraft` and of the modloader, e.g. `fabric = "0.14.21"`.
+    #[serde(default)]
+    versions: HashMap<String, String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct IndexRef {
+    file: String,
+    hash_format: String,
+    hash: String,
+}
+
+/// `index.toml`, listing every file of the pack.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct IndexToml {
+    hash_format: String,
+    files: Vec<IndexFile>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct IndexFile {
+    file: String,
+    hash: String,
+    hash_format: Option<String>,
+    /// The file is a `.pw.toml` describing a file to download.
+    #[serde(default)]
+    metafile: bool,
+    /// The file is only installed if the player doesn't have it.
+    #[serde(default)]
+    preserve: bool,
+}
+
+/// A `.pw.toml` metafile.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct MetaFile {
+    name: String,
+    filename: String,
+    side: Option<String>,
+    download: Download,
+    option: Option<MetaOption>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+struct Download {
+    /// Missing for CurseForge files, which packwiz resolves with the CurseForge API.
+    url: Option<String>,
+    hash_format: String,
+    hash: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct MetaOption {
+    #[serde(default)]
+    optional: bool,
+    #[serde(default)]
+    default: bool,
+    #[serde(default)]
+    description: String,
+}
+
+/// Reads a packwiz modpack from the url of its `pack.toml`, following its index and metafiles.
+/// Files only for the server are left out, the index hashes let unchanged files be skipped on updates.
+/// When `signatures` are given only `pack.toml` needs a signature, its hashes cover the rest of the pack.
+pub fn read_packwiz(url: &str, signatures: Option<&[String]>) -> io::Result<Manifest> {
+    let signed = signatures.is_some();
+    let base_url = &url[..url.rfind('/').map_or(0, |index| index + 1)];
+    let pack: PackToml = toml::from_str(&fetch_signed_string(url, signatures)?).map_err(io::Error::other)?;
+
+    let index_url = file_url(base_url, &pack.index.file);
+    let index_content = fetch_string(&index_url)?;
+    check_hash(&pack.index.file, index_content.as_bytes(), &pack.index.hash_format, &pack.index.hash, signed)?;
+    let index: IndexToml = toml::from_str(&index_content).map_err(io::Error::other)?;
+    // the paths of the index are relative to the folder of index.toml
+    let index_base_url = &index_url[..index_url.rfind('/').map_or(0, |index| index + 1)];
+
+    let mut files: Vec<ManifestFile> = Vec::new();
+    for file in index.files {
+        let hash_format = file.hash_format.unwrap_or(index.hash_format.clone());
+        let policy = if file.preserve {Policy::IfMissing} else {Policy::Overwrite};
+        if !file.metafile {
+            files.push(ManifestFile {
+                url: file_url(index_base_url, &file.file),
+                hashes: HashMap::from([(hash_format, file.hash)]),
+                path: file.file,
+                policy,
+                ..Default::default()
+            });
+            continue;
+        }
+
+        let content = fetch_string(&file_url(index_base_url, &file.file))?;
+        check_hash(&file.file, content.as_bytes(), &hash_format, &file.hash, signed)?;
+        let meta: MetaFile = toml::from_str(&content).map_err(io::Error::other)?;
+        if meta.side.as_deref() == Some("server") {
+            continue;
+        }
+        let url = meta.download.url.ok_or_else(|| io::Error::new(
+            io::ErrorKind::Unsupported, format!("{} has no download url", meta.name)))?;
+        // the downloaded file goes next to its metafile
+        let folder = file.file.rfind('/').map_or("", |index| &file.file[..=index]);
+        let option = meta.option.unwrap_or(MetaOption { optional: false, default: false, description: String::new() });
+        files.push(ManifestFile {
+            path: format!("{}{}", folder, meta.filename),
+            url,
+            hashes: HashMap::from([(meta.download.hash_format, meta.download.hash)]),
+            optional: option.optional,
+            default: option.default,
+            name: Some(meta.name),
+            description: option.description,
+            policy,
+            ..Default::default()
+        });
+    }
+
+    let modloader = pack.versions.iter().find_map(|(name, version)| {
+        let kind = match name.as_str() {
+            "fabric" => ModloaderKind::Fabric,
+            "quilt" => ModloaderKind::Quilt,
+            "forge" => ModloaderKind::Forge,
+            "neoforge" => ModloaderKind::NeoForge,
+            _ => return None,
+        };
+        Some((kind, version.clone()))
+    });
+
+    Ok(Manifest {
+        files,
+        minecraft_version: pack.versions.get("minecraft").cloned(),
+        modloader,
+        ..Default::default()
+    })
+}
+
+fn file_url(base_url: &str, path: &str) -> String {
+    format!("{}{}", base_url, path.replace(' ', "%20"))
+}
+
+/// Checks fetched content against its hash. Unsupported hash formats are not checked, unless the pack is `signed`:
+/// its signature only covers the rest of the pack through the hashes, so they are refused.
+fn check_hash(name: &str, content: &[u8], hash_format: &str, expected: &str, signed: bool) -> io::Result<()> {
+    match bytes_hash(content, hash_format) {
+        Some(hash) if !hash.eq_ignore_ascii_case(expected) => Err(io::Error::new(io::ErrorKind::InvalidData,
+            format!("{} : empreinte {} invalide", name, hash_format))), //lang
+        None if signed => Err(signature_error(format!("{} : empreinte {} non vérifiable", name, hash_format))), //lang
+        _ => Ok(()),
+    }
+}
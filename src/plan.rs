@@ -0,0 +1,400 @@
+use std::fs;
+use std::io::{self, Cursor, Read};
+use std::path::{Path, PathBuf};
+
+use zip::ZipArchive;
+
+use crate::config::{Config, Pack, PackFormat, FILES_TO_REMOVE, get_env_path};
+use crate::curseforge::{parse_curseforge, MANIFEST_FILE};
+use crate::extract::{Rejected, SYMLINK_REASON, is_symlink};
+use crate::files::{bytes_hash, format_size, is_url, list_files};
+use crate::http;
+use crate::manifest::{FileAction, Manifest, ManifestFile};
+use crate::modloader::{InstallStrategy, install_strategy, expected_version_id, is_version_installed};
+use crate::mrpack::{parse_mrpack, INDEX_FILE};
+use crate::packwiz::read_packwiz;
+use crate::record::InstallRecord;
+use crate::signature::{signature_url, verify_bytes};
+
+/// A file the install would write, with its size when it is known.
+#[derive(Debug, Clone)]
+pub struct PlannedFile {
+    pub path: String,
+    pub size: Option<u64>,
+}
+
+/// What installing a pack would do, computed without writing anything: archives are read in memory
+/// and nothing is launched.
+#[derive(Debug, Default)]
+pub struct InstallPlan {
+    pub pack: String,
+    /// Where the modpack would be taken from.
+    pub source: String,
+    /// Size of the archive to download, for the formats coming as an archive.
+    pub archive_size: Option<u64>,
+    /// Folders and files deleted, with their size.
+    pub removed: Vec<(String, u64)>,
+    /// Files written where there was none.
+    pub added: Vec<PlannedFile>,
+    /// Files replacing the player's ones.
+    pub overwritten: Vec<PlannedFile>,
+    /// Files whose missing keys are merged into the player's ones.
+    pub merged: Vec<String>,
+    /// Files left as they are, kept by the player or already up to date.
+    pub kept: Vec<String>,
+    /// Files the extraction policy leaves out.
+    pub rejected: Vec<Rejected>,
+    /// What "Installer <modloader>" would do.
+    pub modloader: String,
+}
+
+/// Kind of a line of the plan, to color it.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlanLine {
+    Title,
+    Section,
+    Removed,
+    Added,
+    Overwritten,
+    Kept,
+    Rejected,
+    Info,
+}
+
+/// The files of an archive or folder, read without extracting anything.
+enum PackContent {
+    Archive(ZipArchive<Cursor<Vec<u8>>>),
+    Folder(PathBuf),
+}
+
+impl PackContent {
+    /// Paths of the files, their size and whether they are symlinks.
+    fn files(&mut self) -> io::Result<Vec<(String, u64, bool)>> {
+        match self {
+            PackContent::Archive(zip) => {
+                let mut files = Vec::new();
+                for index in 0..zip.len() {
+                    let entry = zip.by_index_raw(index).map_err(io::Error::other)?;
+                    if !entry.is_dir() {
+                        files.push((entry.name().to_string(), entry.size(), is_symlink(entry.unix_mode())));
+                    }
+                }
+                Ok(files)
+            }
+            PackContent::Folder(folder) => list_files(folder)?.into_iter()
+                .map(|file| {
+                    let metadata = fs::symlink_metadata(folder.join(&file))?;
+                    Ok((file, metadata.len(), metadata.is_symlink()))
+                })
+                .collect(),
+        }
+    }
+
+    fn read_to_string(&mut self, name: &str) -> io::Result<String> {
+        match self {
+            PackContent::Archive(zip) => {
+                let mut content = String::new();
+                zip.by_name(name).map_err(io::Error::other)?.read_to_string(&mut content)?;
+                Ok(content)
+            }
+            PackContent::Folder(folder) => fs::read_to_string(folder.join(name)),
+        }
+    }
+}
+
+impl InstallPlan {
+    /// Bytes the install needs on disk, and the number of files of unknown size left out of it.
+    pub fn required_space(&self) -> (u64, usize) {
+        let files = self.added.iter().chain(&self.overwritten);
+        let size = self.archive_size.unwrap_or(0) + files.clone().filter_map(|file| file.size).sum::<u64>();
+        (size, files.filter(|file| file.size.is_none()).count())
+    }
+
+    pub fn lines(&self) -> Vec<(PlanLine, String)> {
+        let mut lines = vec![
+            (PlanLine::Title, format!("Prévisualisation de l'installation de {}", self.pack)), //lang
+            (PlanLine::Info, format!("Source : {}", self.source)), //lang
+        ];
+        if let Some(size) = self.archive_size {
+            lines.push((PlanLine::Info, format!("Archive à télécharger : {}", format_size(size)))); //lang
+        }
+
+        let removed_size: u64 = self.removed.iter().map(|(_, size)| size).sum();
+        lines.push((PlanLine::Section, format!("Supprimés : {} ({})", self.removed.len(), format_size(removed_size)))); //lang
+        lines.extend(self.removed.iter().map(|(path, size)| (PlanLine::Removed, format!("{} ({})", path, format_size(*size)))));
+        for (kind, title, files) in [(PlanLine::Added, "Ajoutés", &self.added), (PlanLine::Overwritten, "Remplacés", &self.overwritten)] { //lang
+            lines.push((PlanLine::Section, format!("{} : {}", title, files.len())));
+            lines.extend(files.iter().map(|file| (kind, match file.size {
+                Some(size) => format!("{} ({})", file.path, format_size(size)),
+                None => format!("{} (taille inconnue)", file.path), //lang
+            })));
+        }
+        lines.push((PlanLine::Section, format!("Fusionnés : {}", self.merged.len()))); //lang
+        lines.extend(self.merged.iter().map(|path| (PlanLine::Overwritten, path.clone())));
+        lines.push((PlanLine::Section, format!("Conservés : {}", self.kept.len()))); //lang
+        lines.extend(self.kept.iter().map(|path| (PlanLine::Kept, path.clone())));
+        if !self.rejected.is_empty() {
+            lines.push((PlanLine::Section, format!("Ignorés par sécurité : {}", self.rejected.len()))); //lang
+            lines.extend(self.rejected.iter().map(|entry| (PlanLine::Rejected, format!("{} : {}", entry.path, entry.reason))));
+        }
+
+        let (required, unknown) = self.required_space();
+        let required = match unknown {
+            0 => format!("Espace disque nécessaire : {}", format_size(required)), //lang
+            unknown => format!("Espace disque nécessaire : {} et {} fichier(s) de taille inconnue", format_size(required), unknown), //lang
+        };
+        lines.push((PlanLine::Section, required));
+        lines.push((PlanLine::Info, format!("Espace libéré : {}", format_size(removed_size)))); //lang
+        lines.push((PlanLine::Info, format!("Modloader : {}", self.modloader))); //lang
+
+        lines.into_iter().map(|(kind, line)| {
+            let marker = match kind {
+                PlanLine::Removed => "- ",
+                PlanLine::Added => "+ ",
+                PlanLine::Overwritten => "~ ",
+                PlanLine::Kept => "= ",
+                PlanLine::Rejected => "! ",
+                _ => "",
+            };
+            (kind, format!("{}{}", marker, line))
+        }).collect()
+    }
+}
+
+/// Computes what installing `pack` would do, from the local archive or folder `from` or from its urls.
+/// The signature and hashes of the modpack are checked like during the install.
+pub fn plan_install(config: &Config, pack: &Pack, from: Option<&Path>) -> io::Result<InstallPlan> {
+    let is_mrpack = from.is_some_and(|from| from.extension().is_some_and(|extension| extension == "mrpack"));
+    let format = if is_mrpack {PackFormat::Mrpack} else {pack.format};
+    let mut plan = InstallPlan { pack: pack.name.clone(), source: pack.modpack_url.clone(), ..Default::default() };
+    let signatures = config.require_signatures.then(|| pack.modpack_signatures());
+
+    let (manifest, overrides) = match format {
+        PackFormat::Manifest | PackFormat::Packwiz if from.is_some() => {
+            return Err(io::Error::other("Ce modpack ne peut pas être installé depuis un fichier")); //lang
+        }
+        PackFormat::Packwiz => (read_packwiz(&pack.modpack_url, signatures.as_deref())?, Vec::new()),
+        PackFormat::Manifest => (Manifest::fetch(&pack.modpack_url, signatures.as_deref())?, Vec::new()),
+        PackFormat::Zip => {
+            let mut content = read_content(config, pack, from, &mut plan)?;
+            plan_zip(pack, &content.files()?, &mut plan);
+            plan.modloader = modloader_action(config, pack, &Manifest::default());
+            return Ok(plan);
+        }
+        PackFormat::Mrpack | PackFormat::CurseForge => {
+            let mut content = read_content(config, pack, from, &mut plan)?;
+            let manifest = match format {
+                PackFormat::Mrpack => parse_mrpack(&content.read_to_string(INDEX_FILE)?, Path::new(""))?,
+                _ => parse_curseforge(&content.read_to_string(MANIFEST_FILE)?, Path::new(""),
+                    &pack.curseforge_api_url, pack.curseforge_api_key.as_deref())?,
+            };
+            // the overrides are folders of the archive, their files go at the root of the instance
+            let files = content.files()?;
+            let overrides: Vec<(String, u64, bool)> = manifest.overrides.iter().flat_map(|overrides| {
+                let prefix = format!("{}/", overrides.display());
+                files.iter().filter_map(move |(path, size, symlink)| path.strip_prefix(&prefix).map(|path| (path.to_string(), *size, *symlink)))
+            }).collect();
+            (manifest, overrides)
+        }
+    };
+    plan_files(config, pack, &manifest, &overrides, &mut plan)?;
+    plan.modloader = modloader_action(config, pack, &manifest);
+    Ok(plan)
+}
+
+/// Reads the modpack archive in memory, or lists the folder `from`, checking the archive like `fetch_archive`.
+fn read_content(config: &Config, pack: &Pack, from: Option<&Path>, plan: &mut InstallPlan) -> io::Result<PackContent> {
+    if let Some(from) = from.filter(|from| from.is_dir()) {
+        if config.require_signatures {
+            return Err(io::Error::other("un dossier ne peut pas être authentifié, utilisez l'archive signée")); //lang
+        }
+        plan.source = from.display().to_string();
+        return Ok(PackContent::Folder(from.to_path_buf()));
+    }
+
+    let local: Option<PathBuf> = match from {
+        Some(from) => Some(from.to_path_buf()),
+        None if !is_url(&pack.modpack_url) => Some(PathBuf::from(get_env_path(&pack.modpack_url))),
+        None => None,
+    };
+    let signatures = match from {
+        Some(from) => vec![signature_url(&from.display().to_string())],
+        None => pack.modpack_signatures(),
+    };
+    let content = match local {
+        Some(local) => {
+            plan.source = local.display().to_string();
+            fs::read(&local)?
+        }
+        None => {
+            let urls = pack.modpack_urls();
+            let (url, content) = fetch_first(&urls)?;
+            plan.source = url;
+            plan.archive_size = Some(content.len() as u64);
+            content
+        }
+    };
+    for (algorithm, expected) in &pack.modpack_hashes {
+        if bytes_hash(&content, algorithm).is_some_and(|hash| !hash.eq_ignore_ascii_case(expected)) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, format!("{} : empreinte {} invalide", plan.source, algorithm))); //lang
+        }
+    }
+    if config.require_signatures {
+        verify_bytes(&content, &signatures)?;
+    }
+    Ok(PackContent::Archive(ZipArchive::new(Cursor::new(content)).map_err(io::Error::other)?))
+}
+
+/// Downloads the content of the first working url of `urls` in memory.
+fn fetch_first(urls: &[String]) -> io::Result<(String, Vec<u8>)> {
+    let mut error = io::Error::other("aucune url"); //lang
+    for url in urls {
+        match http::get(url).call() {
+            Ok(response) => {
+                let mut content = Vec::new();
+                response.into_reader().read_to_end(&mut content)?;
+                return Ok((url.clone(), content));
+            }
+            Err(err) => error = io::Error::other(err.to_string()),
+        }
+    }
+    Err(error)
+}
+
+/// A zip modpack deletes `mods` and `config`, then is extracted in the instance folder.
+fn plan_zip(pack: &Pack, files: &[(String, u64, bool)], plan: &mut InstallPlan) {
+    plan.removed.extend(removed_folders(pack));
+    for (path, size, symlink) in files {
+        let removed = FILES_TO_REMOVE.iter().any(|folder| path.split('/').next() == Some(folder));
+        plan_extracted(pack, (path, *size, *symlink), removed, plan);
+    }
+}
+
+/// Sorts a file extracted from the modpack in the instance folder. `removed` is set when its folder is deleted first.
+fn plan_extracted(pack: &Pack, (path, size, symlink): (&str, u64, bool), removed: bool, plan: &mut InstallPlan) {
+    let reason = if symlink {Some(SYMLINK_REASON.to_string())} else {pack.extract_policy.check_path(path)};
+    if let Some(reason) = reason {
+        plan.rejected.push(Rejected { path: path.to_string(), reason });
+        return;
+    }
+    let file = PlannedFile { path: path.to_string(), size: Some(size) };
+    if !removed && Path::new(&pack.instance_folder).join(path).exists() {
+        plan.overwritten.push(file);
+    } else {
+        plan.added.push(file);
+    }
+}
+
+/// The files of a manifest are sorted like `install_files` does, with the optional files the player
+/// selected last time (or the defaults), and the files of the previous install no longer wanted are removed.
+fn plan_files(config: &Config, pack: &Pack, manifest: &Manifest, overrides: &[(String, u64, bool)], plan: &mut InstallPlan) -> io::Result<()> {
+    let record = InstallRecord::load(&config.magic_installer_folder, &pack.id);
+    let selected = manifest.initial_selection(record.as_ref());
+    let mut paths: Vec<String> = Vec::new();
+
+    let files = manifest.selected_files(&selected);
+    if config.require_signatures {
+        let installed: Vec<&ManifestFile> = files.iter().copied()
+            .filter(|file| pack.extract_policy.check_path(&file.relative_path()).is_none())
+            .collect();
+        Manifest::check_hashes(&installed)?;
+    }
+    for file in files {
+        let path = file.relative_path();
+        if let Some(reason) = pack.extract_policy.check_path(&path) {
+            plan.rejected.push(Rejected { path, reason });
+            continue;
+        }
+        let target = file.target(&pack.instance_folder)?;
+        let action = file.action(&target);
+        if file.is_recorded(action, record.as_ref()) {
+            paths.push(path.clone());
+        }
+        match action {
+            FileAction::Keep | FileAction::UpToDate => plan.kept.push(path),
+            FileAction::Merge => plan.merged.push(path),
+            FileAction::Download => {
+                let size = file.size.or_else(|| remote_size(&file.url));
+                let planned = PlannedFile { path, size };
+                if target.exists() {plan.overwritten.push(planned)} else {plan.added.push(planned)}
+            }
+        }
+    }
+    for (path, size, symlink) in overrides {
+        if pack.extract_policy.check_path(path).is_none() {
+            paths.push(path.clone());
+        }
+        plan_extracted(pack, (path, *size, *symlink), false, plan);
+    }
+
+    if let Some(record) = record {
+        for path in record.files.iter().filter(|path| !paths.contains(path)) {
+            if let Ok(metadata) = fs::metadata(Path::new(&pack.instance_folder).join(path)) {
+                plan.removed.push((path.clone(), metadata.len()));
+            }
+        }
+    }
+    Ok(())
+}
+
+/// The folders a zip install deletes before extracting, those that exist with their size.
+pub fn removed_folders(pack: &Pack) -> Vec<(String, u64)> {
+    let instance = Path::new(&pack.instance_folder);
+    FILES_TO_REMOVE.iter()
+        .map(|folder| (folder, instance.join(folder)))
+        .filter(|(_, path)| path.exists())
+        .map(|(folder, path)| (folder.to_string(), folder_size(&path)))
+        .collect()
+}
+
+/// Whether removing the files of `pack` wipes its folders. Only a zip install does, a pack installed file by file
+/// removes its recorded files and leaves the files the player added.
+pub fn wipes_folders(pack: &Pack, record: Option<&InstallRecord>) -> bool {
+    pack.format == PackFormat::Zip && record.is_none_or(|record| record.files.is_empty())
+}
+
+/// What removing the files of `pack` deletes: the folders of a zip install, or the recorded files.
+pub fn removed_files(config: &Config, pack: &Pack) -> Vec<(String, u64)> {
+    let record = InstallRecord::load(&config.magic_installer_folder, &pack.id);
+    if wipes_folders(pack, record.as_ref()) {
+        return removed_folders(pack);
+    }
+    record.map(|record| record.files).unwrap_or_default().into_iter()
+        .filter_map(|path| fs::metadata(Path::new(&pack.instance_folder).join(&path)).ok().map(|metadata| (path, metadata.len())))
+        .collect()
+}
+
+/// Size announced by the server for `url`, without downloading it.
+fn remote_size(url: &str) -> Option<u64> {
+    http::head(url).call().ok()?.header("Content-Length")?.parse().ok()
+}
+
+fn folder_size(folder: &Path) -> u64 {
+    list_files(folder).unwrap_or_default().iter()
+        .filter_map(|file| fs::metadata(folder.join(file)).ok())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Describes what installing the modloader would do, with the versions an imported `manifest` asks for.
+fn modloader_action(config: &Config, pack: &Pack, manifest: &Manifest) -> String {
+    let mut pack = pack.clone();
+    pack.apply_record(&InstallRecord {
+        minecraft_version: manifest.minecraft_version.clone(),
+        modloader: manifest.modloader.as_ref().map(|(kind, _)| *kind),
+        modloader_version: manifest.modloader.as_ref().map(|(_, version)| version.clone()),
+        ..Default::default()
+    });
+    let installed = expected_version_id(&pack)
+        .is_some_and(|version_id| is_version_installed(&config.minecraft_folder, &version_id));
+    let action = match install_strategy(&pack, &config.minecraft_folder) {
+        Some(InstallStrategy::Profile { minecraft_version, loader_version, .. }) => {
+            format!("profil {} {} pour Minecraft {} ajouté à versions", pack.modloader_kind.label(), loader_version, minecraft_version) //lang
+        }
+        Some(InstallStrategy::Installer { urls, filename, .. }) => {
+            format!("{} téléchargé depuis {} puis lancé", filename, urls.first().cloned().unwrap_or_default()) //lang
+        }
+        None => "aucun modloader configuré".to_string(), //lang
+    };
+    if installed {format!("{} (déjà installé)", action)} else {action} //lang
+}
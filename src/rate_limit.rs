@@ -0,0 +1,110 @@
+use std::sync::Mutex;
+use std::thread::sleep;
+use std::time::{Duration, Instant};
+
+/// Step of the + and - keys, in bytes per second.
+pub const RATE_STEP: u64 = 100 * 1024;
+/// Limit set by the - key when downloads are not limited yet.
+const FIRST_LIMIT: u64 = 1024 * 1024;
+
+/// Token bucket shared by every download, so parallel downloads stay under the limit together.
+struct Bucket {
+    /// Bytes per second, 0 for no limit.
+    rate: u64,
+    /// Bytes that can be read without waiting, negative when the downloads are ahead of the limit.
+    tokens: f64,
+    last: Instant,
+}
+
+static BUCKET: Mutex<Option<Bucket>> = Mutex::new(None);
+
+/// Sets the limit of every download, in bytes per second, 0 for no limit.
+pub fn set_rate_limit(rate: u64) {
+    *BUCKET.lock().unwrap() = Some(Bucket { rate, tokens: 0.0, last: Instant::now() });
+}
+
+/// Current limit in bytes per second, 0 for no limit.
+pub fn rate_limit() -> u64 {
+    BUCKET.lock().unwrap().as_ref().map_or(0, |bucket| bucket.rate)
+}
+
+/// Raises the limit by a step, for the + key.
+pub fn increase_rate_limit() {
+    let rate = rate_limit();
+    if rate > 0 {
+        set_rate_limit(rate + RATE_STEP);
+    }
+}
+
+/// Lowers the limit by a step, for the - key. Unlimited downloads get a first limit.
+pub fn decrease_rate_limit() {
+    match rate_limit() {
+        0 => set_rate_limit(FIRST_LIMIT),
+        rate => set_rate_limit(rate.saturating_sub(RATE_STEP).max(RATE_STEP)),
+    }
+}
+
+/// Takes `bytes` from the bucket, sleeping as long as needed to stay under the limit.
+pub fn throttle(bytes: usize) {
+    let wait = {
+        let mut bucket = BUCKET.lock().unwrap();
+        let bucket = match bucket.as_mut() {
+            Some(bucket) if bucket.rate > 0 => bucket,
+            _ => return,
+        };
+        let rate = bucket.rate as f64;
+        // at most one second of unused bandwidth is kept
+        bucket.tokens = (bucket.tokens + bucket.last.elapsed().as_secs_f64() * rate).min(rate);
+        bucket.last = Instant::now();
+        bucket.tokens -= bytes as f64;
+        if bucket.tokens < 0.0 {-bucket.tokens / rate} else {0.0}
+    };
+    if wait > 0.0 {
+        sleep(Duration::from_secs_f64(wait));
+    }
+}
+
+/// Parses a rate like `500K`, `2Mo`, `300B` or `800` (Ko/s without unit) into bytes per second.
+pub fn parse_rate(rate: &str) -> Option<u64> {
+    let rate = rate.trim().to_uppercase();
+    let rate = rate.trim_end_matches("/S");
+    let (number, unit) = match rate.char_indices().find(|(_, c)| c.is_ascii_alphabetic()) {
+        Some((index, _)) => rate.split_at(index),
+        None => (rate, "K"),
+    };
+    let multiplier = match unit {
+        "B" | "O" => 1,
+        "K" | "KB" | "KO" => 1024,
+        "M" | "MB" | "MO" => 1024 * 1024,
+        "G" | "GB" | "GO" => 1024 * 1024 * 1024,
+        _ => return None,
+    };
+    number.trim().parse::<f64>().ok().map(|number| (number * multiplier as f64) as u64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_rate_units() {
+        assert_eq!(parse_rate("800"), Some(800 * 1024));
+        assert_eq!(parse_rate("500K"), Some(500 * 1024));
+        assert_eq!(parse_rate("2Mo/s"), Some(2 * 1024 * 1024));
+        assert_eq!(parse_rate("1.5 MB"), Some(1024 * 1024 * 3 / 2));
+        assert_eq!(parse_rate("1g"), Some(1024 * 1024 * 1024));
+    }
+
+    #[test]
+    fn parse_rate_bytes() {
+        assert_eq!(parse_rate("500B"), Some(500));
+        assert_eq!(parse_rate("300o/s"), Some(300));
+    }
+
+    #[test]
+    fn parse_rate_invalid() {
+        assert_eq!(parse_rate("fast"), None);
+        assert_eq!(parse_rate("10T"), None);
+        assert_eq!(parse_rate(""), None);
+    }
+}
@@ -0,0 +1,80 @@
+use std::fs::{self, File};
+use std::io::{Write, self};
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+
+use crate::files::create_folder;
+use crate::modloader::ModloaderKind;
+
+pub const RECORDS_FOLDER: &str = "records";
+
+/// What the installer put in an instance folder, kept in `magic_installer\records\<pack id>.json`
+/// so updates and removals know which files belong to the modpack.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct InstallRecord {
+    pub pack: String,
+    /// Paths of the installed files, relative to the instance folder.
+    #[serde(default)]
+    pub files: Vec<String>,
+    /// Paths of the optional files the player selected.
+    #[serde(default)]
+    pub optional: Vec<String>,
+    /// Paths of every optional file offered, so the ones added to the manifest since get their default.
+    /// `None` for records written before it was kept.
+    #[serde(default)]
+    pub offered: Option<Vec<String>>,
+    /// Url or path the modpack archive was taken from, the mirror that worked when several are configured.
+    #[serde(default)]
+    pub source: Option<String>,
+    /// Minecraft and modloader versions required by an imported modpack, they replace the configured ones.
+    #[serde(default)]
+    pub minecraft_version: Option<String>,
+    #[serde(default)]
+    pub modloader: Option<ModloaderKind>,
+    #[serde(default)]
+    pub modloader_version: Option<String>,
+}
+
+impl InstallRecord {
+    pub fn path(magic_installer_folder: &str, pack_id: &str) -> PathBuf {
+        Path::new(magic_installer_folder).join(RECORDS_FOLDER).join(format!("{}.json", pack_id))
+    }
+
+    /// Loads the record of `pack_id`, `None` if the pack was never installed.
+    pub fn load(magic_installer_folder: &str, pack_id: &str) -> Option<InstallRecord> {
+        let content = fs::read_to_string(InstallRecord::path(magic_installer_folder, pack_id)).ok()?;
+        serde_json::from_str(&content).ok()
+    }
+
+    pub fn save(&self, magic_installer_folder: &str) -> io::Result<()> {
+        create_folder(&format!("{}{}", magic_installer_folder, RECORDS_FOLDER));
+        let mut file = File::create(InstallRecord::path(magic_installer_folder, &self.pack))?;
+        file.write_all(serde_json::to_string_pretty(self)?.as_bytes())?;
+        Ok(())
+    }
+
+    /// Deletes the recorded files from the instance folder, files already gone are ignored.
+    pub fn remove_files(&self, instance_folder: &str) -> io::Result<()> {
+        for path in &self.files {
+            match fs::remove_file(Path::new(instance_folder).join(path)) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// Whether the optional file at `path` was offered in this install. Older records only know the selected files,
+    /// so every file is taken as offered.
+    pub fn has_offered(&self, path: &str) -> bool {
+        self.offered.as_ref().is_none_or(|offered| offered.iter().any(|offered| offered == path))
+    }
+
+    pub fn remove(magic_installer_folder: &str, pack_id: &str) -> io::Result<()> {
+        match fs::remove_file(InstallRecord::path(magic_installer_folder, pack_id)) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
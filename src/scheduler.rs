@@ -0,0 +1,260 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs::{create_dir_all, remove_file, rename};
+use std::path::{Path, PathBuf};
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+use std::time::Instant;
+
+use crate::cache::Cache;
+use crate::files::{download_file, verify_hashes, DownloadStatus};
+
+pub const DEFAULT_CONCURRENT_DOWNLOADS: usize = 4;
+/// Attempts of a file before the whole batch fails, each one trying every mirror.
+pub const DOWNLOAD_ATTEMPTS: usize = 3;
+/// Extension of the files being downloaded, put in place once the whole batch succeeded.
+const PART_EXTENSION: &str = "part";
+
+/// A file to download, checked against `hashes` once downloaded.
+#[derive(Debug, Clone)]
+pub struct DownloadJob {
+    pub name: String,
+    pub path: PathBuf,
+    pub urls: Vec<String>,
+    pub hashes: HashMap<String, String>,
+}
+
+/// Progress of a batch, shared between the download threads and the page drawing it.
+#[derive(Debug)]
+pub struct BatchProgress {
+    pub files_total: usize,
+    pub files_done: usize,
+    /// Files taken from the cache instead of downloaded.
+    pub files_cached: usize,
+    pub bytes: u64,
+    pub start: Instant,
+    /// Names of the files being downloaded.
+    pub active: Vec<String>,
+    /// Files downloaded from another url than their first one, with the url used.
+    pub mirrors: Vec<(String, String)>,
+    /// Set when a file failed all its attempts, the remaining files are not started.
+    pub error: Option<String>,
+    workers: usize,
+}
+
+impl BatchProgress {
+    pub fn is_finished(&self) -> bool {
+        self.workers == 0
+    }
+
+    /// Average rate of the batch, in bytes per second.
+    pub fn rate(&self) -> f32 {
+        self.bytes as f32 / self.start.elapsed().as_secs_f32().max(0.001)
+    }
+}
+
+/// Downloads `jobs` with at most `concurrency` files at a time, in the background.
+/// A failed file is retried on its own, the files already downloaded are kept.
+/// Files are downloaded next to their target and only replace it once every file of the batch is checked,
+/// a failed batch leaves the targets untouched.
+/// Files with hashes are taken from `cache` when it has them, and added to it once downloaded.
+pub fn start_batch(jobs: Vec<DownloadJob>, concurrency: usize, cache: Arc<Mutex<Cache>>) -> Arc<Mutex<BatchProgress>> {
+    let workers = concurrency.max(1).min(jobs.len());
+    let progress = Arc::new(Mutex::new(BatchProgress {
+        files_total: jobs.len(),
+        files_done: 0,
+        files_cached: 0,
+        bytes: 0,
+        start: Instant::now(),
+        active: Vec::new(),
+        mirrors: Vec::new(),
+        error: None,
+        workers,
+    }));
+    let targets: Arc<Vec<PathBuf>> = Arc::new(jobs.iter().map(|job| job.path.clone()).collect());
+    let queue = Arc::new(Mutex::new(VecDeque::from(jobs)));
+
+    for _ in 0..workers {
+        let queue = Arc::clone(&queue);
+        let progress = Arc::clone(&progress);
+        let cache = Arc::clone(&cache);
+        let targets = Arc::clone(&targets);
+        thread::spawn(move || {
+            loop {
+                if progress.lock().unwrap().error.is_some() {
+                    break;
+                }
+                let job = match queue.lock().unwrap().pop_front() {
+                    Some(job) => job,
+                    None => break,
+                };
+                if !job.hashes.is_empty() && cache_fetch(&cache, &job) {
+                    let mut progress = progress.lock().unwrap();
+                    progress.files_done += 1;
+                    progress.files_cached += 1;
+                    continue;
+                }
+                progress.lock().unwrap().active.push(job.name.clone());
+                let result = download_job(&job, &progress);
+                if result.is_ok() && !job.hashes.is_empty() {
+                    // the cache only saves time, a file that can't be cached is still installed
+                    let _ = cache.lock().unwrap().store(&part_path(&job.path), &job.hashes);
+                }
+                let mut progress = progress.lock().unwrap();
+                progress.active.retain(|name| name != &job.name);
+                match result {
+                    Ok(()) => progress.files_done += 1,
+                    Err(error) => progress.error = Some(format!("{} : {}", job.name, error)),
+                }
+            }
+            let mut progress = progress.lock().unwrap();
+            // the last worker puts the batch in place, the page sees it finished once this is done
+            if progress.workers == 1 {
+                finish_batch(&targets, &mut progress);
+            }
+            progress.workers -= 1;
+        });
+    }
+    progress
+}
+
+/// Path a file is downloaded to before replacing `target`.
+fn part_path(target: &Path) -> PathBuf {
+    let mut path = target.as_os_str().to_owned();
+    path.push(".");
+    path.push(PART_EXTENSION);
+    PathBuf::from(path)
+}
+
+/// Moves the downloaded files over their targets, or removes them when the batch failed.
+fn finish_batch(targets: &[PathBuf], progress: &mut BatchProgress) {
+    for target in targets {
+        let part = part_path(target);
+        if progress.error.is_some() {
+            let _ = remove_file(&part);
+        } else if let Err(error) = rename(&part, target) {
+            progress.error = Some(format!("{} : {}", target.display(), error));
+            let _ = remove_file(&part);
+        }
+    }
+}
+
+fn cache_fetch(cache: &Mutex<Cache>, job: &DownloadJob) -> bool {
+    if let Some(folder) = job.path.parent() {
+        let _ = create_dir_all(folder);
+    }
+    cache.lock().unwrap().fetch(&job.hashes, &part_path(&job.path)).unwrap_or(false)
+}
+
+fn download_job(job: &DownloadJob, progress: &Arc<Mutex<BatchProgress>>) -> Result<(), String> {
+    if let Some(folder) = job.path.parent() {
+        create_dir_all(folder).map_err(|err| err.to_string())?;
+    }
+    let part = part_path(&job.path);
+    let mut error = String::new();
+    for _ in 0..DOWNLOAD_ATTEMPTS {
+        let (tx, rx) = mpsc::channel();
+        let path = part.to_string_lossy().to_string();
+        let urls = job.urls.clone();
+        let handle = thread::spawn(move || download_file(&path, &urls, tx));
+
+        // bytes of a failed attempt are not counted in the batch
+        let mut received: u64 = 0;
+        let mut source: Option<String> = None;
+        for status in rx {
+            match status {
+                DownloadStatus::Received(bytes) => {
+                    received += bytes as u64;
+                    progress.lock().unwrap().bytes += bytes as u64;
+                }
+                DownloadStatus::Mirror(_) => {
+                    progress.lock().unwrap().bytes -= received;
+                    received = 0;
+                }
+                DownloadStatus::Downloaded(url) => source = Some(url),
+                DownloadStatus::Error(err) => error = err,
+                DownloadStatus::Downloading(_) => {}
+            }
+        }
+        if let Err(err) = handle.join().unwrap() {
+            error = err.to_string();
+        }
+
+        let result = match source {
+            Some(source) => verify_hashes(&part, &job.hashes).map(|_| source).map_err(|err| err.to_string()),
+            None => Err(error.clone()),
+        };
+        match result {
+            Ok(source) => {
+                if Some(&source) != job.urls.first() {
+                    progress.lock().unwrap().mirrors.push((job.name.clone(), source));
+                }
+                return Ok(());
+            }
+            Err(err) => {
+                error = err;
+                progress.lock().unwrap().bytes -= received;
+                let _ = remove_file(&part);
+            }
+        }
+    }
+    Err(error)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env;
+    use std::fs;
+    use std::time::Duration;
+
+    use super::*;
+    use crate::files::bytes_hash;
+    use crate::http::tests::serve;
+
+    fn run_batch(name: &str, files: &[&'static str]) -> (PathBuf, Option<String>) {
+        let folder = env::temp_dir().join(format!("magic_installer_scheduler_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&folder);
+        fs::create_dir_all(&folder).unwrap();
+        let url = serve(vec![("/a.jar", b"new a".to_vec()), ("/b.jar", b"new b".to_vec())]);
+        let jobs = files.iter().map(|file| {
+            let path = folder.join(file.trim_start_matches('/'));
+            fs::write(&path, "old").unwrap();
+            let content = format!("new {}", &file[1..2]);
+            DownloadJob {
+                name: file.to_string(),
+                path,
+                urls: vec![format!("{}{}", url, file)],
+                hashes: HashMap::from([("sha256".to_string(), bytes_hash(content.as_bytes(), "sha256").unwrap())]),
+            }
+        }).collect();
+        let cache = Arc::new(Mutex::new(Cache::open(&folder.join("installer").to_string_lossy(), 0)));
+
+        let progress = start_batch(jobs, 1, cache);
+        while !progress.lock().unwrap().is_finished() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        let error = progress.lock().unwrap().error.clone();
+        (folder, error)
+    }
+
+    #[test]
+    fn a_finished_batch_replaces_the_files() {
+        let (folder, error) = run_batch("finished", &["/a.jar", "/b.jar"]);
+        assert_eq!(error, None);
+        assert_eq!(fs::read_to_string(folder.join("a.jar")).unwrap(), "new a");
+        assert_eq!(fs::read_to_string(folder.join("b.jar")).unwrap(), "new b");
+        assert!(!part_path(&folder.join("a.jar")).exists());
+        fs::remove_dir_all(folder).unwrap();
+    }
+
+    #[test]
+    fn a_failed_batch_leaves_the_files_untouched() {
+        // c.jar is not served, a.jar is downloaded before it fails
+        let (folder, error) = run_batch("failed", &["/a.jar", "/c.jar"]);
+        assert!(error.is_some());
+        assert_eq!(fs::read_to_string(folder.join("a.jar")).unwrap(), "old");
+        assert_eq!(fs::read_to_string(folder.join("c.jar")).unwrap(), "old");
+        assert!(!part_path(&folder.join("a.jar")).exists());
+        assert!(!part_path(&folder.join("c.jar")).exists());
+        fs::remove_dir_all(folder).unwrap();
+    }
+}
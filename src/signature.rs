@@ -0,0 +1,189 @@
+use std::error::Error;
+use std::fmt;
+use std::fs;
+use std::io::{self, Read};
+use std::path::Path;
+
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey, SIGNATURE_LENGTH};
+
+use crate::config::get_env_path;
+use crate::files::{fetch_string, is_url};
+use crate::http;
+
+/// Ed25519 public key the modpacks and releases are signed with, in base64.
+const PUBLIC_KEY: &str = include_str!("../assets/signing_key.pub");
+/// Suffix of the detached signature of a file next to it or to its url, when no signature url is configured.
+pub const SIGNATURE_EXTENSION: &str = ".sig";
+
+/// A file whose signature is missing or doesn't match the embedded key.
+/// Wrapped in an `io::Error` so the install steps can tell it apart, see `is_signature_error`.
+#[derive(Debug)]
+pub struct SignatureError(String);
+
+impl fmt::Display for SignatureError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl Error for SignatureError {}
+
+pub fn signature_error(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, SignatureError(message))
+}
+
+/// Whether `error` comes from a failed signature check.
+pub fn is_signature_error(error: &io::Error) -> bool {
+    error.get_ref().is_some_and(|error| error.is::<SignatureError>())
+}
+
+/// Where the signature of the file at `source` is by default, `.sig` being added before the query string of an url.
+/// Share links don't have a file next to them, their signature url must be configured, see `signature_sources`.
+pub fn signature_url(source: &str) -> String {
+    match source.split_once('?') {
+        Some((address, query)) => format!("{}{}?{}", address, SIGNATURE_EXTENSION, query),
+        None => format!("{}{}", source, SIGNATURE_EXTENSION),
+    }
+}
+
+/// Where the signature of a file downloaded from `sources` is: the configured `signature_urls`,
+/// else next to each source.
+pub fn signature_sources(signature_urls: &[String], sources: &[String]) -> Vec<String> {
+    match signature_urls.is_empty() {
+        true => sources.iter().map(|source| signature_url(source)).collect(),
+        false => signature_urls.to_vec(),
+    }
+}
+
+fn public_key() -> VerifyingKey {
+    let key: [u8; 32] = STANDARD.decode(PUBLIC_KEY.trim()).ok()
+        .and_then(|key| key.try_into().ok())
+        .expect("assets/signing_key.pub must be a base64 Ed25519 public key");
+    VerifyingKey::from_bytes(&key).expect("assets/signing_key.pub must be a base64 Ed25519 public key")
+}
+
+/// Checks `content` against a signature, either the raw 64 bytes or their base64.
+pub fn verify(content: &[u8], signature: &[u8]) -> io::Result<()> {
+    verify_with(&public_key(), content, signature)
+}
+
+fn verify_with(key: &VerifyingKey, content: &[u8], signature: &[u8]) -> io::Result<()> {
+    let signature: Vec<u8> = match signature.len() {
+        SIGNATURE_LENGTH => signature.to_vec(),
+        _ => STANDARD.decode(String::from_utf8_lossy(signature).trim())
+            .map_err(|_| signature_error("signature illisible".to_string()))?, //lang
+    };
+    let signature = Signature::from_slice(&signature).map_err(|_| signature_error("signature illisible".to_string()))?; //lang
+    key.verify(content, &signature).map_err(|_| signature_error("signature invalide".to_string())) //lang
+}
+
+/// Reads the signature at `url`, an url or a local path.
+fn fetch_signature(url: &str) -> io::Result<Vec<u8>> {
+    if !is_url(url) {
+        return fs::read(get_env_path(url));
+    }
+    let response = http::get(url).call().map_err(|err| io::Error::other(err.to_string()))?;
+    let mut signature = Vec::new();
+    response.into_reader().take(1024).read_to_end(&mut signature)?;
+    Ok(signature)
+}
+
+/// Checks `content` against the signature at `url`.
+fn verify_content(content: &[u8], url: &str) -> io::Result<()> {
+    let signature = fetch_signature(url)
+        .map_err(|err| signature_error(format!("signature introuvable à {} : {}", url, err)))?; //lang
+    verify(content, &signature)
+}
+
+/// Checks the file at `path` against one of the signatures at `signatures`, see `signature_sources`.
+pub fn verify_file(path: &Path, signatures: &[String]) -> io::Result<()> {
+    verify_bytes(&fs::read(path)?, signatures)
+}
+
+/// Checks `content` against one of the signatures at `signatures`. Mirrors are tried in order,
+/// so one missing its signature doesn't fail the check, the error of the first one is kept.
+pub fn verify_bytes(content: &[u8], signatures: &[String]) -> io::Result<()> {
+    let mut error: Option<io::Error> = None;
+    for url in signatures {
+        match verify_content(content, url) {
+            Ok(()) => return Ok(()),
+            Err(err) => {error.get_or_insert(err);}
+        }
+    }
+    Err(error.unwrap_or_else(|| signature_error("aucune signature".to_string()))) //lang
+}
+
+/// Fetches the text at `url`, checked against one of `signatures` when they are given.
+pub fn fetch_signed_string(url: &str, signatures: Option<&[String]>) -> io::Result<String> {
+    let body = fetch_string(url)?;
+    if let Some(signatures) = signatures {
+        verify_bytes(body.as_bytes(), signatures)?;
+    }
+    Ok(body)
+}
+
+#[cfg(test)]
+mod tests {
+    use ed25519_dalek::{Signer, SigningKey};
+
+    use super::*;
+
+    fn signing_key(seed: u8) -> SigningKey {
+        SigningKey::from_bytes(&[seed; 32])
+    }
+
+    #[test]
+    fn embedded_key_is_valid() {
+        public_key();
+    }
+
+    #[test]
+    fn verify_accepts_raw_and_base64_signatures() {
+        let key = signing_key(1);
+        let signature = key.sign(b"modpack").to_bytes();
+        assert!(verify_with(&key.verifying_key(), b"modpack", &signature).is_ok());
+        let encoded = format!("{}\n", STANDARD.encode(signature));
+        assert!(verify_with(&key.verifying_key(), b"modpack", encoded.as_bytes()).is_ok());
+    }
+
+    #[test]
+    fn verify_rejects_tampered_content_and_other_keys() {
+        let key = signing_key(1);
+        let signature = key.sign(b"modpack").to_bytes();
+        let tampered = verify_with(&key.verifying_key(), b"modpack!", &signature).unwrap_err();
+        assert!(is_signature_error(&tampered));
+        let other_key = verify_with(&signing_key(2).verifying_key(), b"modpack", &signature).unwrap_err();
+        assert!(is_signature_error(&other_key));
+    }
+
+    #[test]
+    fn verify_rejects_unreadable_signatures() {
+        let key = signing_key(1).verifying_key();
+        for signature in [&b""[..], b"not base64 !", &[0; 12]] {
+            assert!(is_signature_error(&verify_with(&key, b"modpack", signature).unwrap_err()));
+        }
+    }
+
+    #[test]
+    fn signature_url_goes_before_the_query() {
+        assert_eq!(signature_url("https://example.com/pack.zip"), "https://example.com/pack.zip.sig");
+        assert_eq!(signature_url("https://www.dropbox.com/s/a/pack.zip?dl=1"), "https://www.dropbox.com/s/a/pack.zip.sig?dl=1");
+        assert_eq!(signature_url("modpacks\\pack.zip"), "modpacks\\pack.zip.sig");
+    }
+
+    #[test]
+    fn configured_signature_urls_come_first() {
+        let sources = vec!["https://example.com/pack.zip".to_string(), "https://mirror.com/pack.zip".to_string()];
+        assert_eq!(signature_sources(&[], &sources), vec!["https://example.com/pack.zip.sig", "https://mirror.com/pack.zip.sig"]);
+        let configured = vec!["https://www.dropbox.com/scl/fi/a/pack.zip.sig?dl=1".to_string()];
+        assert_eq!(signature_sources(&configured, &sources), configured);
+    }
+
+    #[test]
+    fn signature_errors_are_told_apart() {
+        assert!(is_signature_error(&signature_error("signature invalide".to_string())));
+        assert!(!is_signature_error(&io::Error::other("réseau")));
+    }
+}
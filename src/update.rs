@@ -0,0 +1,105 @@
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, remove_file};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use serde::Deserialize;
+
+use crate::config::{VERSION, direct_download_url};
+use crate::files::fetch_string;
+use crate::signature::signature_sources;
+
+/// Extension of the new binary while it is downloaded, then of the replaced one until the next start.
+pub const NEW_EXTENSION: &str = "new";
+pub const OLD_EXTENSION: &str = "old";
+
+/// The latest release, as served by `update_url`:
+/// `{"version": "v2.2.0", "url": "https://...", "mirrors": [...], "hashes": {"sha256": "..."}}`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct Release {
+    pub version: String,
+    pub url: String,
+    #[serde(default)]
+    pub mirrors: Vec<String>,
+    /// Expected hashes of the binary keyed by algorithm, an update without a supported one is refused.
+    #[serde(default)]
+    pub hashes: HashMap<String, String>,
+    /// Url of the signature of the binary, when it can't be next to `url`.
+    #[serde(default)]
+    pub signature_url: Option<String>,
+}
+
+impl Release {
+    /// The url of the binary followed by its mirrors.
+    pub fn urls(&self) -> Vec<String> {
+        let mut urls = vec![direct_download_url(&self.url)];
+        urls.extend(self.mirrors.iter().map(|url| direct_download_url(url)));
+        urls
+    }
+
+    /// Where the signature of the binary is: `signature_url`, else next to each of `urls`.
+    pub fn signatures(&self) -> Vec<String> {
+        let configured: Vec<String> = self.signature_url.iter().map(|url| direct_download_url(url)).collect();
+        signature_sources(&configured, &self.urls())
+    }
+}
+
+/// Fetches the release at `update_url`, returns it only if it is newer than the running installer.
+pub fn check_update(update_url: &str) -> io::Result<Option<Release>> {
+    let body = fetch_string(update_url)?;
+    let release: Release = serde_json::from_str(&body).map_err(io::Error::other)?;
+    Ok(if version_numbers(&release.version) > version_numbers(VERSION) {Some(release)} else {None})
+}
+
+/// Numbers of a version like `v2.1.1`, compared from left to right. Suffixes like `-beta` are ignored.
+fn version_numbers(version: &str) -> Vec<u64> {
+    version.trim().trim_start_matches('v').split('.')
+        .map(|part| part.chars().take_while(char::is_ascii_digit).collect::<String>().parse().unwrap_or(0))
+        .collect()
+}
+
+/// Path the new binary is downloaded to, next to the running one so it can be renamed over it.
+pub fn download_path() -> io::Result<PathBuf> {
+    Ok(env::current_exe()?.with_extension(NEW_EXTENSION))
+}
+
+/// Puts the downloaded binary at `new` in place of the running one.
+/// Windows can't overwrite a running executable but can rename it, so the old one is moved aside
+/// and deleted on the next start by `remove_old_executable`.
+pub fn replace_executable(new: &Path) -> io::Result<()> {
+    let current = env::current_exe()?;
+    let old = current.with_extension(OLD_EXTENSION);
+    if old.exists() {
+        remove_file(&old)?;
+    }
+    fs::rename(&current, &old)?;
+    if let Err(err) = fs::rename(new, &current) {
+        // puts the running binary back so the installer still starts next time
+        fs::rename(&old, &current)?;
+        return Err(err);
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&current, fs::Permissions::from_mode(0o755))?;
+    }
+    Ok(())
+}
+
+/// Deletes the binary left by the last update, if any.
+pub fn remove_old_executable() -> io::Result<()> {
+    let old = env::current_exe()?.with_extension(OLD_EXTENSION);
+    if old.exists() {
+        remove_file(old)?;
+    }
+    Ok(())
+}
+
+/// Starts the updated installer at `executable` with the same arguments. The caller exits right after.
+/// `executable` must be read before the update, the running binary has been renamed since.
+pub fn relaunch(executable: &Path, args: &[String]) -> io::Result<()> {
+    Command::new(executable).args(args.iter().skip(1)).spawn()?;
+    Ok(())
+}